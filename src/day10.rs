@@ -1,4 +1,4 @@
-use std::{collections::HashSet, usize};
+use std::usize;
 
 use crate::{
     PartFn,
@@ -18,18 +18,41 @@ const DOWN: Dir = (0, 1);
 const LEFT: Dir = (-1, 0);
 const RIGHT: Dir = (1, 0);
 
+#[derive(Debug, Clone)]
+pub struct Terrain {
+    pub trailhead: u32,
+    pub peak: u32,
+    pub min_climb: u32,
+    pub max_climb: u32,
+}
+
+impl Default for Terrain {
+    fn default() -> Self {
+        Terrain {
+            trailhead: 0,
+            peak: 9,
+            min_climb: 1,
+            max_climb: 1,
+        }
+    }
+}
+
+fn height(c: &char) -> Option<u32> {
+    c.to_digit(10)
+}
+
 fn get_dirs() -> Vec<Dir> {
     vec![UP, RIGHT, DOWN, LEFT]
 }
 
-fn find_trail_heads(input: &[String]) -> Vec<Pos> {
+fn find_trail_heads(input: &[String], terrain: &Terrain) -> Vec<Pos> {
     input
         .iter()
         .enumerate()
         .flat_map(|(y, s)| {
             s.chars()
                 .enumerate()
-                .filter(|(_, c)| c == &'0')
+                .filter(|(_, c)| height(c) == Some(terrain.trailhead))
                 .map(|(x, _)| (x, y))
                 .collect::<Vec<Pos>>()
         })
@@ -52,17 +75,20 @@ fn add_dir(pos: Pos, dir: Dir, grid: &Grid) -> Option<Pos> {
     }
 }
 
-fn next_higher(current: &char, next: char) -> bool {
-    let current = current.to_digit(10).unwrap();
-    let next = next.to_digit(10).unwrap();
-    next == current + 1
+fn next_higher(current: &char, next: char, terrain: &Terrain) -> bool {
+    match (height(current), height(&next)) {
+        (Some(current), Some(next)) if next > current => {
+            (terrain.min_climb..=terrain.max_climb).contains(&(next - current))
+        }
+        _ => false,
+    }
 }
 
-fn is_final_step(current: &char) -> bool {
-    current == &'9'
+fn is_final_step(current: &char, terrain: &Terrain) -> bool {
+    height(current) == Some(terrain.peak)
 }
 
-fn get_next_step(pos: Pos, grid: &Grid) -> Vec<Dir> {
+fn get_next_step(pos: Pos, grid: &Grid, terrain: &Terrain) -> Vec<Dir> {
     let (x, y) = pos;
     let current = grid[y][x];
     let dirs = get_dirs();
@@ -74,69 +100,169 @@ fn get_next_step(pos: Pos, grid: &Grid) -> Vec<Dir> {
                 None
             }
         })
-        .filter(|(_, (x, y))| next_higher(&current, grid[*y][*x]))
+        .filter(|(_, (x, y))| next_higher(&current, grid[*y][*x], terrain))
         .map(|(dir, _)| dir.clone())
         .collect()
 }
 
-fn step(pos: Pos, mut visited: Vec<Pos>, grid: &Grid) -> Vec<Pos> {
-    let (x, y) = pos;
-    if is_final_step(&grid[y][x]) {
-        vec![pos]
-    } else {
-        let next = get_next_step(pos, grid);
-        visited.push(pos);
-        let res = next
-            .iter()
-            .filter_map(|n| {
-                if let Some(npos) = add_dir(pos, *n, grid) {
-                    Some(step(npos, visited.clone(), grid))
-                } else {
-                    None
-                }
-            })
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrailScore {
+    pub head: Pos,
+    pub score: usize,
+    pub rating: usize,
+}
+
+type Reach = (Pos, Vec<usize>, usize);
+
+pub fn score_trails(grid: &Grid, terrain: &Terrain) -> Vec<TrailScore> {
+    let width = grid.iter().map(|v| v.len()).max().unwrap_or(0);
+    let index = |(x, y): Pos| y * width + x;
+    let mut by_height: Vec<Vec<Pos>> = vec![vec![]; 10];
+    grid.iter().enumerate().for_each(|(y, v)| {
+        v.iter().enumerate().for_each(|(x, c)| {
+            if let Some(h) = height(c) {
+                by_height[h as usize].push((x, y));
+            }
+        })
+    });
+
+    let (peak, trailhead) = (terrain.peak as usize, terrain.trailhead as usize);
+    let (Some(peaks), Some(heads)) = (by_height.get(peak), by_height.get(trailhead)) else {
+        return vec![];
+    };
+    let mut slot = vec![usize::MAX; grid.len() * width];
+    let mut layers: Vec<Vec<Reach>> = vec![vec![]; 10];
+    layers[peak] = peaks
+        .iter()
+        .enumerate()
+        .map(|(id, pos)| {
+            slot[index(*pos)] = id;
+            (*pos, vec![id], 1)
+        })
+        .collect();
+
+    for h in (trailhead..peak).rev() {
+        let mut layer: Vec<Reach> = vec![];
+        (terrain.min_climb..=terrain.max_climb)
+            .filter_map(|climb| layers.get(h + climb as usize))
             .flatten()
-            .collect::<HashSet<Pos>>();
-        res.iter().copied().collect()
+            .for_each(|(pos, peaks, rating)| {
+                get_dirs()
+                    .iter()
+                    .filter_map(|dir| add_dir(*pos, *dir, grid))
+                    .filter(|(x, y)| height(&grid[*y][*x]) == Some(h as u32))
+                    .for_each(|next| {
+                        let i = index(next);
+                        if slot[i] == usize::MAX {
+                            slot[i] = layer.len();
+                            layer.push((next, vec![], 0));
+                        }
+                        let (_, reach, total) = &mut layer[slot[i]];
+                        reach.extend_from_slice(peaks);
+                        *total += rating;
+                    });
+            });
+        layer.iter_mut().for_each(|(_, reach, _)| {
+            reach.sort_unstable();
+            reach.dedup();
+        });
+        layers[h] = layer;
+        if let Some(done) = layers.get_mut(h + terrain.max_climb as usize) {
+            *done = vec![];
+        }
     }
+
+    heads
+        .iter()
+        .map(|head| {
+            let (score, rating) = layers[trailhead]
+                .get(slot[index(*head)])
+                .map_or((0, 0), |(_, peaks, rating)| (peaks.len(), *rating));
+            TrailScore {
+                head: *head,
+                score,
+                rating,
+            }
+        })
+        .collect()
 }
 
 fn part1(input: &Vec<String>) -> usize {
     let grid = to_matrix(input);
-    let heads = find_trail_heads(input);
-    heads
+    score_trails(&grid, &Terrain::default())
         .iter()
-        .map(|pos| step(*pos, vec![], &grid))
-        .map(|v| v.len())
-        .sum::<usize>()
+        .map(|t| t.score)
+        .sum()
 }
 
-fn step2(pos: Pos, mut visited: Vec<Pos>, grid: &Grid) -> usize {
+fn part2(input: &Vec<String>) -> usize {
+    let grid = to_matrix(input);
+    score_trails(&grid, &Terrain::default())
+        .iter()
+        .map(|t| t.rating)
+        .sum()
+}
+
+fn collect_trails(
+    pos: Pos,
+    trail: &mut Vec<Pos>,
+    grid: &Grid,
+    terrain: &Terrain,
+    limit: usize,
+    res: &mut Vec<Vec<Pos>>,
+) {
+    if res.len() >= limit {
+        return;
+    }
+    trail.push(pos);
     let (x, y) = pos;
-    if is_final_step(&grid[y][x]) {
-        1
+    if is_final_step(&grid[y][x], terrain) {
+        res.push(trail.clone());
     } else {
-        let next = get_next_step(pos, grid);
-        visited.push(pos);
-        next.iter()
-            .map(|n| {
-                if let Some(npos) = add_dir(pos, *n, grid) {
-                    step2(npos, visited.clone(), grid)
-                } else {
-                    0
-                }
-            })
-            .sum()
+        get_next_step(pos, grid, terrain)
+            .iter()
+            .filter_map(|dir| add_dir(pos, *dir, grid))
+            .for_each(|next| collect_trails(next, trail, grid, terrain, limit, res));
     }
+    trail.pop();
 }
 
-fn part2(input: &Vec<String>) -> usize {
-    let grid = to_matrix(input);
-    let heads = find_trail_heads(input);
-    heads
+pub fn trails(head: Pos, grid: &Grid, terrain: &Terrain, limit: Option<usize>) -> Vec<Vec<Pos>> {
+    let mut res = vec![];
+    collect_trails(
+        head,
+        &mut vec![],
+        grid,
+        terrain,
+        limit.unwrap_or(usize::MAX),
+        &mut res,
+    );
+    res
+}
+
+pub fn all_trails(
+    input: &[String],
+    terrain: &Terrain,
+    limit: Option<usize>,
+) -> Vec<(Pos, Vec<Vec<Pos>>)> {
+    let grid = to_matrix(&input.to_vec());
+    find_trail_heads(input, terrain)
         .iter()
-        .map(|pos| step2(*pos, vec![], &grid))
-        .sum::<usize>()
+        .map(|head| (*head, trails(*head, &grid, terrain, limit)))
+        .collect()
+}
+
+pub fn render_trail(trail: &[Pos], grid: &Grid) -> String {
+    grid.iter()
+        .enumerate()
+        .map(|(y, v)| {
+            v.iter()
+                .enumerate()
+                .map(|(x, c)| if trail.contains(&(x, y)) { *c } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
 }
 
 #[cfg(test)]
@@ -145,7 +271,9 @@ mod tests {
 
     use crate::util::{file_io::get_test_input, util::to_matrix};
 
-    use super::{get_next_step, part1, part2, step};
+    use super::{
+        Terrain, all_trails, get_next_step, part1, part2, render_trail, score_trails, trails,
+    };
 
     #[test]
     fn test_part1() {
@@ -162,7 +290,7 @@ mod tests {
                 .map(|s| s.to_string())
                 .collect(),
         );
-        let next = get_next_step((1, 1), &grid);
+        let next = get_next_step((1, 1), &grid, &Terrain::default());
         assert_eq!(next, vec![(0, -1), (1, 0), (0, 1), (-1, 0)]);
     }
 
@@ -189,9 +317,97 @@ mod tests {
             .collect(),
         );
 
-        let res = step((0, 0), vec![], &grid);
-        assert_eq!(res.len(), 3);
-        let res = step((2, 0), vec![], &grid);
+        let res = score_trails(&grid, &Terrain::default());
+        let score = |head| res.iter().find(|t| t.head == head).unwrap().score;
+        assert_eq!(score((0, 0)), 3);
+        assert_eq!(score((2, 0)), 3);
+    }
+
+    #[test]
+    fn test_trails() {
+        let grid = to_matrix(
+            &["0123", "1234", "8765", "9876"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        );
+        let res = trails((0, 0), &grid, &Terrain::default(), None);
+        assert_eq!(res.len(), 16);
+        assert!(res.iter().all(|t| t.len() == 10));
+        let res = trails((0, 0), &grid, &Terrain::default(), Some(3));
         assert_eq!(res.len(), 3);
+        assert_eq!(
+            render_trail(&res[0], &grid),
+            ["0123", "...4", "...5", "9876"].join("\n")
+        );
+
+        let input = get_test_input(10);
+        let count: usize = all_trails(&input, &Terrain::default(), None)
+            .iter()
+            .map(|(_, t)| t.len())
+            .sum();
+        assert_eq!(count, 81);
+    }
+
+    #[test]
+    fn test_impassable() {
+        let grid = to_matrix(
+            &[
+                "...0...", "...1...", "...2...", "6543456", "7.....7", "8.....8", "9.....9",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        );
+        let res = score_trails(&grid, &Terrain::default());
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].score, 2);
+        assert_eq!(res[0].rating, 2);
+    }
+
+    #[test]
+    fn test_terrain_rules() {
+        let grid = to_matrix(
+            &["1357", "2.#7", "3456"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        );
+        let terrain = Terrain {
+            trailhead: 1,
+            peak: 7,
+            min_climb: 1,
+            max_climb: 2,
+        };
+        let res = score_trails(&grid, &terrain);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].score, 2);
+        assert_eq!(res[0].rating, 2);
+        assert_eq!(trails((0, 0), &grid, &terrain, None).len(), 2);
+
+        let high = |trailhead, peak| Terrain {
+            trailhead,
+            peak,
+            ..Terrain::default()
+        };
+        assert!(score_trails(&grid, &high(1, 10)).is_empty());
+        assert!(score_trails(&grid, &high(10, 7)).is_empty());
+    }
+
+    #[test]
+    fn test_large_map() {
+        let size = 1000;
+        let grid: Vec<Vec<char>> = (0..size)
+            .map(|y| {
+                (0..size)
+                    .map(|x| char::from_digit(((x + y) % 10) as u32, 10).unwrap())
+                    .collect()
+            })
+            .collect();
+        let res = score_trails(&grid, &Terrain::default());
+        assert_eq!(res.len(), size * size / 10);
+        assert_eq!(res[0].head, (0, 0));
+        assert_eq!(res[0].score, 10);
+        assert_eq!(res[0].rating, 512);
     }
 }
@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use crate::{PartFn, puzzle_result::PuzzleResult};
 
 pub fn day2() -> PuzzleResult<PartFn, PartFn, usize, usize> {
@@ -10,70 +12,175 @@ fn line_to_vec_usize(s: &String) -> Vec<usize> {
         .collect()
 }
 
-fn is_positave_delta(a: usize, b: &usize) -> bool {
-    a as isize - *b as isize >= 0
+#[derive(Debug, Clone)]
+pub struct Tolerance {
+    pub min_step: usize,
+    pub max_step: usize,
+    pub max_removals: usize,
 }
 
-fn is_safe(report: &Vec<usize>) -> bool {
-    let delta = is_positave_delta(report[0], &report[1]);
-    let same = |a: usize, b: &usize| {
-        let delta1 = is_positave_delta(a, b);
-        delta == delta1
-    };
-    let diff = |a: usize, b: &usize| {
-        let diff = (a as isize - *b as isize).abs();
-        diff > 0 && diff < 4
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            min_step: 1,
+            max_step: 3,
+            max_removals: 0,
+        }
+    }
+}
+
+fn valid_step(a: usize, b: usize, increasing: bool, tolerance: &Tolerance) -> bool {
+    let step = if increasing {
+        b.checked_sub(a)
+    } else {
+        a.checked_sub(b)
     };
-    report
-        .iter()
+    step.is_some_and(|step| step >= tolerance.min_step && step <= tolerance.max_step)
+}
+
+fn longest_kept(report: &[usize], increasing: bool, tolerance: &Tolerance) -> usize {
+    let window = tolerance.max_removals + 1;
+    let mut kept = vec![1; report.len()];
+    for i in 1..report.len() {
+        kept[i] = (i.saturating_sub(window)..i)
+            .filter(|j| valid_step(report[*j], report[i], increasing, tolerance))
+            .map(|j| kept[j] + 1)
+            .max()
+            .unwrap_or(1);
+    }
+    kept.iter()
         .enumerate()
-        .skip(1)
-        .map(|(i, x)| same(report[i - 1], x) && diff(report[i - 1], x))
-        .all(|b| b)
+        .filter(|(i, _)| *i + tolerance.max_removals + 1 >= report.len())
+        .map(|(_, k)| *k)
+        .max()
+        .unwrap_or(0)
 }
 
-fn part1(input: &Vec<String>) -> usize {
-    let reports: Vec<Vec<usize>> = input.iter().map(line_to_vec_usize).collect();
-    reports.iter().map(is_safe).filter(|b| *b).count()
-}
-
-fn try_make_safe(report: &Vec<usize>) -> Vec<usize> {
-    let safe: Vec<Vec<usize>> = (0..report.len())
-        .map(|i| {
-            let mut report = report.clone();
-            report.remove(i);
-            report
-        })
-        .map(|r| (is_safe(&r), r))
-        .filter(|(b, _)| *b)
-        .map(|(_, r)| r)
-        .collect();
+pub fn is_safe(report: &[usize], tolerance: &Tolerance) -> bool {
+    let kept = [true, false]
+        .iter()
+        .map(|increasing| longest_kept(report, *increasing, tolerance))
+        .max()
+        .unwrap_or(0);
+    report.len() <= kept + tolerance.max_removals
+}
 
-    if let Some(safe) = safe.first() {
-        safe.clone()
-    } else {
-        report.clone()
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Problem {
+    Direction,
+    Step,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnosis {
+    pub report: Vec<usize>,
+    pub violation: Option<(usize, Problem)>,
+    pub removed: Option<usize>,
+    pub safe: bool,
+}
+
+fn first_violation<I>(levels: I, tolerance: &Tolerance) -> Option<(usize, Problem)>
+where
+    I: Iterator<Item = usize> + Clone,
+{
+    let pairs = levels.clone().zip(levels.skip(1));
+    let rising = pairs.clone().filter(|(a, b)| b > a).count();
+    let falling = pairs.clone().filter(|(a, b)| b < a).count();
+    let increasing = rising >= falling;
+    pairs.enumerate().find_map(|(i, (a, b))| {
+        if (increasing && b < a) || (!increasing && b > a) {
+            Some((i, Problem::Direction))
+        } else if !valid_step(a, b, increasing, tolerance) {
+            Some((i, Problem::Step))
+        } else {
+            None
+        }
+    })
+}
+
+pub fn diagnose(report: &[usize], tolerance: &Tolerance) -> Diagnosis {
+    let violation = first_violation(report.iter().copied(), tolerance);
+    // only reports a single removed level, even when max_removals allows more
+    let removed = match violation {
+        Some(_) if tolerance.max_removals > 0 => (0..report.len()).find(|skip| {
+            let levels = report
+                .iter()
+                .enumerate()
+                .filter(move |(i, _)| i != skip)
+                .map(|(_, level)| *level);
+            first_violation(levels, tolerance).is_none()
+        }),
+        _ => None,
+    };
+    Diagnosis {
+        report: report.to_vec(),
+        violation,
+        removed,
+        safe: is_safe(report, tolerance),
     }
 }
 
-fn make_safe(report: &Vec<usize>) -> Vec<usize> {
-    if is_safe(report) {
-        report.clone()
-    } else {
-        try_make_safe(report)
+impl Display for Diagnosis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let report: Vec<String> = self.report.iter().map(|l| l.to_string()).collect();
+        let safe = if self.safe { "yes" } else { "no" };
+        let violation = match self.violation {
+            Some((i, problem)) => {
+                let problem = match problem {
+                    Problem::Direction => "direction",
+                    Problem::Step => "step",
+                };
+                format!("{} {} ({})", self.report[i], self.report[i + 1], problem)
+            }
+            None => "-".to_string(),
+        };
+        let removed = self.removed.map_or("-".to_string(), |i| {
+            format!("{} at index {}", self.report[i], i)
+        });
+        write!(
+            f,
+            "{:<24} | {:<4} | {:<16} | {}",
+            report.join(" "),
+            safe,
+            violation,
+            removed
+        )
     }
 }
 
+pub fn diagnostics_table(input: &[String], tolerance: &Tolerance) -> String {
+    let header = format!(
+        "{:<24} | {:<4} | {:<16} | {}",
+        "report", "safe", "violation", "removed"
+    );
+    let rows: Vec<String> = input
+        .iter()
+        .map(line_to_vec_usize)
+        .map(|r| diagnose(&r, tolerance).to_string())
+        .collect();
+    [vec![header], rows].concat().join("\n")
+}
+
+fn part1(input: &Vec<String>) -> usize {
+    let reports: Vec<Vec<usize>> = input.iter().map(line_to_vec_usize).collect();
+    let tolerance = Tolerance::default();
+    reports.iter().filter(|r| is_safe(r, &tolerance)).count()
+}
+
 fn part2(input: &Vec<String>) -> usize {
     let reports: Vec<Vec<usize>> = input.iter().map(line_to_vec_usize).collect();
-    reports.iter().map(make_safe).filter(is_safe).count()
+    let tolerance = Tolerance {
+        max_removals: 1,
+        ..Tolerance::default()
+    };
+    reports.iter().filter(|r| is_safe(r, &tolerance)).count()
 }
 
 #[cfg(test)]
 mod tests {
     use crate::util::file_io::get_test_input;
 
-    use super::{part1, part2};
+    use super::{Problem, Tolerance, diagnose, diagnostics_table, is_safe, part1, part2};
 
     #[test]
     fn test_part1() {
@@ -88,4 +195,96 @@ mod tests {
         let res = part2(&input);
         assert_eq!(res, 4);
     }
+
+    #[test]
+    fn test_is_safe_with_removals() {
+        let tolerance = |max_removals| Tolerance {
+            max_removals,
+            ..Tolerance::default()
+        };
+        assert!(!is_safe(&[1, 9, 2, 8, 3, 4], &tolerance(1)));
+        assert!(is_safe(&[1, 9, 2, 8, 3, 4], &tolerance(2)));
+        assert!(is_safe(&[9, 1, 2, 3, 4], &tolerance(1)));
+        assert!(is_safe(&[1, 2, 3, 4, 9], &tolerance(1)));
+        assert!(!is_safe(&[1, 2, 3, 9, 8], &tolerance(1)));
+        assert!(is_safe(&[1, 2, 3, 9, 8], &tolerance(2)));
+        assert!(is_safe(&[4, 1], &tolerance(0)));
+        assert!(is_safe(&[5, 5, 5], &tolerance(2)));
+        assert!(!is_safe(&[5, 5, 5], &tolerance(1)));
+    }
+
+    #[test]
+    fn test_is_safe_thresholds() {
+        let wide = Tolerance {
+            min_step: 2,
+            max_step: 5,
+            max_removals: 0,
+        };
+        assert!(is_safe(&[1, 6, 8, 13], &wide));
+        assert!(!is_safe(&[1, 2, 4], &wide));
+        assert!(!is_safe(&[1, 6, 8, 13], &Tolerance::default()));
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let dampener = Tolerance {
+            max_removals: 1,
+            ..Tolerance::default()
+        };
+        let res = diagnose(&[1, 3, 2, 4, 5], &dampener);
+        assert_eq!(res.violation, Some((1, Problem::Direction)));
+        assert_eq!(res.removed, Some(1));
+        assert!(res.safe);
+
+        let res = diagnose(&[9, 7, 6, 2, 1], &dampener);
+        assert_eq!(res.violation, Some((2, Problem::Step)));
+        assert_eq!(res.removed, None);
+        assert!(!res.safe);
+
+        let res = diagnose(&[1, 3, 2, 4, 5], &Tolerance::default());
+        assert_eq!(res.removed, None);
+        assert!(!res.safe);
+
+        let two = Tolerance {
+            max_removals: 2,
+            ..Tolerance::default()
+        };
+        let res = diagnose(&[1, 9, 2, 8, 3, 4], &two);
+        assert_eq!(res.violation, Some((0, Problem::Step)));
+        assert_eq!(res.removed, None);
+        assert!(res.safe);
+        assert_eq!(res.safe, is_safe(&[1, 9, 2, 8, 3, 4], &two));
+    }
+
+    #[test]
+    fn test_diagnostics_table() {
+        let input = get_test_input(2);
+        let dampener = Tolerance {
+            max_removals: 1,
+            ..Tolerance::default()
+        };
+        let table = diagnostics_table(&input, &dampener);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert_eq!(
+            lines[0],
+            "report                   | safe | violation        | removed"
+        );
+        assert_eq!(
+            lines[1],
+            "7 6 4 2 1                | yes  | -                | -"
+        );
+        assert_eq!(
+            lines[4],
+            "1 3 2 4 5                | yes  | 3 2 (direction)  | 3 at index 1"
+        );
+        assert_eq!(
+            lines[5],
+            "8 6 4 4 1                | yes  | 4 4 (step)       | 4 at index 2"
+        );
+        assert_eq!(
+            lines.iter().skip(1).filter(|l| l.contains("| yes")).count(),
+            part2(&input)
+        );
+    }
 }
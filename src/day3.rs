@@ -1,78 +1,223 @@
-use std::usize;
+use std::fmt::Display;
 
 use crate::{PartFn, puzzle_result::PuzzleResult};
 
-use regex::Regex;
-
 pub fn day3() -> PuzzleResult<PartFn, PartFn, usize, usize> {
     PuzzleResult::new(3, Some(part1), Some(part2))
 }
 
-fn extract_numbers(s: &str) -> (usize, String) {
-    let regex = Regex::new(r"mul\(\d+,\d+\)").unwrap();
-    if let Some(reg) = regex.captures(s) {
-        let m = reg
-            .iter()
-            .filter_map(|cap| cap.map(|m| m.as_str()))
-            .collect::<Vec<&str>>()
-            .join(" ");
-        let start = m.find("(").unwrap() + 1;
-        let end = m.find(")").unwrap();
-        let m = &m[start..end];
-        let nums: Vec<usize> = m.split(",").map(|s| s.parse::<usize>().unwrap()).collect();
-        let end = reg.get(0).map(|m| m.end()).unwrap();
-        (nums[0] * nums[1], s[end..].to_string())
-    } else {
-        (0, String::new())
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Mul,
+    Do,
+    Dont,
+}
+
+impl Op {
+    const ALL: [Op; 3] = [Op::Mul, Op::Dont, Op::Do];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Op::Mul => "mul",
+            Op::Do => "do",
+            Op::Dont => "don't",
+        }
+    }
+
+    fn arity(&self) -> usize {
+        match self {
+            Op::Mul => 2,
+            Op::Do | Op::Dont => 0,
+        }
     }
 }
 
-fn process_line(s: &String) -> usize {
-    let mut s = s.clone();
-    let mut acc = 0;
-    while !s.is_empty() {
-        let (num, new_str) = extract_numbers(&s);
-        acc += num;
-        s = new_str;
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub offset: usize,
+    pub op: Op,
+    pub args: Vec<usize>,
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let args: Vec<String> = self.args.iter().map(|a| a.to_string()).collect();
+        write!(f, "{}({})", self.op.name(), args.join(","))
     }
-    acc
 }
 
-fn part1(input: &Vec<String>) -> usize {
-    input.iter().map(process_line).sum()
+fn parse_number(s: &[u8], i: &mut usize) -> Option<usize> {
+    let start = *i;
+    while *i < s.len() && *i - start < 3 && s[*i].is_ascii_digit() {
+        *i += 1;
+    }
+    if *i == start || (*i < s.len() && s[*i].is_ascii_digit()) {
+        None
+    } else {
+        std::str::from_utf8(&s[start..*i]).ok()?.parse().ok()
+    }
 }
 
-fn take_from_to(s: &String, start: &str, end: &str) -> (String, String) {
-    let start_reg = Regex::new(start).unwrap();
-    let end_reg = Regex::new(end).unwrap();
-    if let Some(reg) = start_reg.captures(s) {
-        let i = reg.get(0).map(|m| m.start()).unwrap();
-        if let Some(reg) = end_reg.captures(s) {
-            let j = reg.get(0).map(|m| m.start()).unwrap();
-            (s[i..j].to_string(), s[j + 5..].to_string())
+fn parse_instruction(s: &[u8], offset: usize, op: Op) -> Option<(Instruction, usize)> {
+    let name = op.name().as_bytes();
+    let mut i = offset + name.len();
+    if !s[offset..].starts_with(name) || s.get(i) != Some(&b'(') {
+        return None;
+    }
+    i += 1;
+    let mut args = Vec::with_capacity(op.arity());
+    for n in 0..op.arity() {
+        if n > 0 {
+            if s.get(i) != Some(&b',') {
+                return None;
+            }
+            i += 1;
+        }
+        args.push(parse_number(s, &mut i)?);
+    }
+    if s.get(i) != Some(&b')') {
+        return None;
+    }
+    Some((Instruction { offset, op, args }, i + 1))
+}
+
+pub fn tokenize(s: &str) -> Vec<Instruction> {
+    let bytes = s.as_bytes();
+    let mut instructions = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some((instruction, end)) = Op::ALL
+            .iter()
+            .find_map(|op| parse_instruction(bytes, i, *op))
+        {
+            instructions.push(instruction);
+            i = end;
         } else {
-            (s[i..].to_string(), String::new())
+            i += 1;
         }
-    } else {
-        (String::new(), String::new())
     }
+    instructions
 }
 
-fn get_enabled_sections(mut s: String) -> String {
-    s = String::from("do") + s.as_str();
-    let mut res = String::new();
-    while !s.is_empty() {
-        let (take, rest) = take_from_to(&s, "do", "don't");
-        res.push_str(take.as_str());
-        s = rest;
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    // byte offset into the input as run, with lines from run_lines joined by '\n'
+    pub offset: usize,
+    pub instruction: Instruction,
+    pub executed: bool,
+    pub total: usize,
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = if self.executed { "executed" } else { "skipped" };
+        write!(
+            f,
+            "{:>6}  {:<16}{:<10}total {}",
+            self.offset,
+            self.instruction.to_string(),
+            status,
+            self.total
+        )
     }
-    res
+}
+
+#[derive(Debug, Clone)]
+pub struct Interpreter {
+    pub conditionals: bool,
+    pub enabled: bool,
+    pub total: usize,
+    pub executed: Vec<Instruction>,
+    pub trace: Option<Vec<TraceEntry>>,
+    consumed: usize,
+}
+
+impl Interpreter {
+    pub fn new(conditionals: bool) -> Interpreter {
+        Interpreter {
+            conditionals,
+            enabled: true,
+            total: 0,
+            executed: vec![],
+            trace: None,
+            consumed: 0,
+        }
+    }
+
+    pub fn with_trace(conditionals: bool) -> Interpreter {
+        Interpreter {
+            trace: Some(vec![]),
+            ..Interpreter::new(conditionals)
+        }
+    }
+
+    fn execute(&mut self, instruction: Instruction) {
+        let executed = match instruction.op {
+            Op::Mul if self.enabled => {
+                self.total += instruction.args[0] * instruction.args[1];
+                true
+            }
+            Op::Do if self.conditionals => {
+                self.enabled = true;
+                true
+            }
+            Op::Dont if self.conditionals => {
+                self.enabled = false;
+                true
+            }
+            _ => false,
+        };
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(TraceEntry {
+                offset: self.consumed + instruction.offset,
+                instruction: instruction.clone(),
+                executed,
+                total: self.total,
+            });
+        }
+        if executed {
+            self.executed.push(instruction);
+        }
+    }
+
+    pub fn run(&mut self, s: &str) -> usize {
+        tokenize(s).into_iter().for_each(|i| self.execute(i));
+        self.consumed += s.len();
+        self.total
+    }
+
+    pub fn run_lines(&mut self, lines: &[String]) -> usize {
+        lines.iter().enumerate().for_each(|(i, line)| {
+            if i > 0 {
+                self.consumed += 1;
+            }
+            self.run(line);
+        });
+        self.total
+    }
+}
+
+pub fn trace(input: &[String], conditionals: bool) -> String {
+    let mut interpreter = Interpreter::with_trace(conditionals);
+    interpreter.run_lines(input);
+    interpreter
+        .trace
+        .unwrap_or_default()
+        .iter()
+        .map(|entry| entry.to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn part1(input: &Vec<String>) -> usize {
+    input
+        .iter()
+        .map(|line| Interpreter::new(false).run(line))
+        .sum()
 }
 
 fn part2(input: &Vec<String>) -> usize {
-    let input = input.iter().fold(String::new(), |a, b| a + b.as_str());
-    let input = get_enabled_sections(input);
-    process_line(&input)
+    Interpreter::new(true).run_lines(input)
 }
 
 #[cfg(test)]
@@ -80,7 +225,7 @@ mod tests {
 
     use regex::Regex;
 
-    use super::{part1, part2};
+    use super::{Instruction, Interpreter, Op, part1, part2, tokenize, trace};
     use crate::util::file_io::get_test_input;
 
     #[test]
@@ -111,4 +256,102 @@ mod tests {
             assert_eq!(&input[0][i..i + 5], "don't");
         }
     }
+
+    #[test]
+    fn test_tokenize() {
+        let res = tokenize("mul(1,2)mul(1234,5)mul(12,3!don't()do()");
+        assert_eq!(
+            res,
+            vec![
+                Instruction {
+                    offset: 0,
+                    op: Op::Mul,
+                    args: vec![1, 2],
+                },
+                Instruction {
+                    offset: 28,
+                    op: Op::Dont,
+                    args: vec![],
+                },
+                Instruction {
+                    offset: 35,
+                    op: Op::Do,
+                    args: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interpreter() {
+        let input = get_test_input(3);
+        let mut interpreter = Interpreter::new(true);
+        assert_eq!(interpreter.run(&input[0]), 48);
+        let ops: Vec<Op> = interpreter.executed.iter().map(|i| i.op).collect();
+        assert_eq!(ops, vec![Op::Mul, Op::Dont, Op::Do, Op::Mul]);
+    }
+
+    fn run(lines: &[&str]) -> usize {
+        let lines: Vec<String> = lines.iter().map(|s| s.to_string()).collect();
+        Interpreter::new(true).run_lines(&lines)
+    }
+
+    #[test]
+    fn test_regression_boundaries() {
+        assert_eq!(run(&["don't()mul(2,3)undo()mul(4,5)"]), 20);
+        assert_eq!(run(&["don't(mul(2,3)"]), 6);
+        assert_eq!(run(&["don't ()mul(2,3)"]), 6);
+        assert_eq!(run(&["dont()mul(2,3)"]), 6);
+        assert_eq!(run(&["do_not()mul(2,3)"]), 6);
+        assert_eq!(run(&["don't()do(mul(2,3)"]), 0);
+        assert_eq!(run(&["don't()do)mul(2,3)"]), 0);
+        assert_eq!(run(&["don't()do()()mul(2,3)"]), 6);
+    }
+
+    #[test]
+    fn test_regression_nesting() {
+        assert_eq!(run(&["mul(mul(1,2),3)"]), 2);
+        assert_eq!(run(&["mul(1,mul(2,3))"]), 6);
+        assert_eq!(run(&["mul(1000,2)mul(999,2)"]), 1998);
+        assert_eq!(run(&["mul( 2,3)mul(2 ,3)mul(-2,3)mul(2,3"]), 0);
+        assert_eq!(run(&["mmul(2,3)mul(2,3))"]), 12);
+    }
+
+    #[test]
+    fn test_regression_lines() {
+        assert_eq!(run(&["mul(2,3)don't()", "mul(4,5)", "do()mul(1,1)"]), 7);
+        assert_eq!(run(&["mul(2,", "3)"]), 0);
+        assert_eq!(run(&["don", "'t()mul(2,3)"]), 6);
+        assert_eq!(run(&["don't()", "undo()mul(2,3)"]), 6);
+    }
+
+    #[test]
+    fn test_trace() {
+        let input = get_test_input(3);
+        let res = trace(&input, true);
+        let lines: Vec<&str> = res.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "     1  mul(2,4)        executed  total 8");
+        assert_eq!(lines[1], "    20  don't()         executed  total 8");
+        assert_eq!(lines[2], "    28  mul(5,5)        skipped   total 8");
+        assert_eq!(lines[5], "    64  mul(8,5)        executed  total 48");
+
+        let lines = ["mul(1,1)".to_string(), "mul(2,2)".to_string()];
+        let res = trace(&lines, false);
+        assert_eq!(
+            res,
+            "     0  mul(1,1)        executed  total 1\n     9  mul(2,2)        executed  total 5"
+        );
+
+        let mut interpreter = Interpreter::with_trace(false);
+        interpreter.run("mul(1,1)");
+        interpreter.run("xmul(2,2)");
+        let offsets: Vec<usize> = interpreter
+            .trace
+            .unwrap_or_default()
+            .iter()
+            .map(|entry| entry.offset)
+            .collect();
+        assert_eq!(offsets, vec![0, 9]);
+    }
 }
@@ -9,136 +9,205 @@ pub fn day4() -> PuzzleResult<PartFn, PartFn, usize, usize> {
 }
 
 type Pos = (usize, usize);
-type PosOption = (Option<usize>, Option<usize>);
-
-fn check<F>(mat: &Grid, term: &str, pos: Pos, step: F) -> Option<String>
-where
-    F: Fn(usize, usize) -> PosOption,
-{
-    let (mut x, mut y) = pos;
-    let bound_check = |x: usize, y: usize| y < mat.len() && x < mat[y].len();
-    let advance = |x: usize, y: usize| match step(x, y) {
-        (Some(new_x), Some(new_y)) => {
-            if bound_check(new_x, new_y) {
-                Some((new_x, new_y))
-            } else {
-                None
-            }
-        }
-        _ => None,
+type Dir = (isize, isize);
+
+const DIRECTIONS: [Dir; 8] = [
+    (1, 0),
+    (1, -1),
+    (1, 1),
+    (-1, 0),
+    (-1, -1),
+    (-1, 1),
+    (0, 1),
+    (0, -1),
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    pub wrap: bool,
+    pub palindromes_twice: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub start: Pos,
+    pub direction: Dir,
+    pub word: String,
+}
+
+fn cell_at(mat: &Grid, pos: Pos, direction: Dir, i: usize, wrap: bool) -> Option<char> {
+    let y = pos.1 as isize + direction.1 * i as isize;
+    let y = if wrap {
+        y.rem_euclid(mat.len() as isize)
+    } else {
+        y
     };
-    let mut s = String::new();
-    for _ in 0..term.len() {
-        s.push(mat[y][x]);
-        if let Some((new_x, new_y)) = advance(x, y) {
-            x = new_x;
-            y = new_y;
-        } else {
-            break;
-        }
-    }
-    Some(s)
+    let row = mat.get(usize::try_from(y).ok()?)?;
+    let x = pos.0 as isize + direction.0 * i as isize;
+    let x = if wrap && !row.is_empty() {
+        x.rem_euclid(row.len() as isize)
+    } else {
+        x
+    };
+    row.get(usize::try_from(x).ok()?).copied()
 }
 
-fn check_pos(mat: &Grid, term: &str, pos: Pos) -> usize {
-    let steps = [
-        |x: usize, y: usize| (Some(x + 1), Some(y)), // Right
-        |x: usize, y: usize| (Some(x + 1), y.checked_sub(1)), // Top-right diagonal
-        |x: usize, y: usize| (Some(x + 1), Some(y + 1)), // Bottom-right diagonal
-        |x: usize, y: usize| (x.checked_sub(1), Some(y)), // Left
-        |x: usize, y: usize| (x.checked_sub(1), y.checked_sub(1)), // Top-left diagonal
-        |x: usize, y: usize| (x.checked_sub(1), Some(y + 1)), // Bottom-left diagonal
-        |x: usize, y: usize| (Some(x), Some(y + 1)), // Down
-        |x: usize, y: usize| (Some(x), y.checked_sub(1)), // Up
-    ];
-
-    let mapper = |step: &fn(usize, usize) -> PosOption| check(mat, term, pos, step);
-    let predicate = |s: &String| s == term;
-
-    steps.iter().filter_map(mapper).filter(predicate).count()
+fn matches_at(mat: &Grid, word: &str, pos: Pos, direction: Dir, wrap: bool) -> bool {
+    word.chars()
+        .enumerate()
+        .all(|(i, c)| cell_at(mat, pos, direction, i, wrap) == Some(c))
 }
 
-fn get_positions(mat: &Grid) -> Vec<Pos> {
+fn counted(word: &str, direction: Dir, options: &SearchOptions) -> bool {
+    let canonical = direction.1 > 0 || (direction.1 == 0 && direction.0 > 0);
+    if word.chars().count() == 1 {
+        direction == DIRECTIONS[0]
+    } else if options.palindromes_twice || canonical {
+        true
+    } else {
+        !word.chars().eq(word.chars().rev())
+    }
+}
+
+pub fn search(mat: &Grid, words: &[&str], options: &SearchOptions) -> Vec<Match> {
     mat.iter()
         .enumerate()
-        .flat_map(|(y, v)| {
-            v.iter()
-                .enumerate()
-                .filter(|(_, c)| c == &&'X')
-                .map(|(x, _)| (x, y))
-                .collect::<Vec<Pos>>()
+        .flat_map(|(y, v)| (0..v.len()).map(move |x| (x, y)))
+        .flat_map(|pos| {
+            words.iter().flat_map(move |word| {
+                DIRECTIONS
+                    .iter()
+                    .filter(|direction| counted(word, **direction, options))
+                    .filter(move |direction| matches_at(mat, word, pos, **direction, options.wrap))
+                    .map(move |direction| Match {
+                        start: pos,
+                        direction: *direction,
+                        word: word.to_string(),
+                    })
+            })
         })
         .collect()
 }
 
 fn part1(input: &Vec<String>) -> usize {
     let mat = &to_matrix(input);
-    get_positions(mat)
-        .iter()
-        .map(|pos| check_pos(mat, "XMAS", *pos))
-        .sum()
+    search(mat, &["XMAS"], &SearchOptions::default()).len()
 }
 
-fn other_char_of(m: char, c: char) -> Option<char> {
-    if m == 'M' && c == 'S' {
-        Some('S')
-    } else if m == 'S' && c == 'M' {
-        Some('M')
-    } else {
-        None
-    }
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pattern {
+    cells: Vec<Vec<Option<char>>>,
 }
 
-fn is_part_of_x(c: char) -> Option<char> {
-    if c == 'M' || c == 'S' { Some(c) } else { None }
-}
+impl Pattern {
+    pub fn parse(rows: &[&str]) -> Pattern {
+        let concrete: Vec<(usize, usize, char)> = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c != '.')
+                    .map(move |(x, c)| (x, y, c))
+            })
+            .collect();
+        let (Some(min_x), Some(min_y)) = (
+            concrete.iter().map(|(x, _, _)| *x).min(),
+            concrete.iter().map(|(_, y, _)| *y).min(),
+        ) else {
+            return Pattern { cells: vec![] };
+        };
+        let max_x = concrete.iter().map(|(x, _, _)| *x).max().unwrap_or(min_x);
+        let max_y = concrete.iter().map(|(_, y, _)| *y).max().unwrap_or(min_y);
+        let mut cells = vec![vec![None; max_x - min_x + 1]; max_y - min_y + 1];
+        concrete
+            .iter()
+            .for_each(|(x, y, c)| cells[y - min_y][x - min_x] = Some(*c));
+        Pattern { cells }
+    }
+
+    fn height(&self) -> usize {
+        self.cells.len()
+    }
 
-fn check_x(mat: &Grid, pos: &Pos) -> bool {
-    let (x, y) = *pos;
-    if y > 0 && y < mat.len() - 1 && x > 0 && x < mat[y].len() - 1 {
-        if let Some(ul) = is_part_of_x(mat[y - 1][x - 1]) {
-            if other_char_of(ul, mat[y + 1][x + 1]).is_some() {
-                if let Some(ur) = is_part_of_x(mat[y - 1][x + 1]) {
-                    if other_char_of(ur, mat[y + 1][x - 1]).is_some() {
-                        true
-                    } else {
-                        false
-                    }
-                } else {
-                    false
+    fn width(&self) -> usize {
+        self.cells.iter().map(|row| row.len()).max().unwrap_or(0)
+    }
+
+    fn get(&self, x: usize, y: usize) -> Option<char> {
+        self.cells[y].get(x).copied().flatten()
+    }
+
+    fn rotate(&self) -> Pattern {
+        let (w, h) = (self.width(), self.height());
+        let cells = (0..w)
+            .map(|y| (0..h).map(|x| self.get(y, h - 1 - x)).collect())
+            .collect();
+        Pattern { cells }
+    }
+
+    fn reflect(&self) -> Pattern {
+        let w = self.width();
+        let cells = (0..self.height())
+            .map(|y| (0..w).map(|x| self.get(w - 1 - x, y)).collect())
+            .collect();
+        Pattern { cells }
+    }
+
+    pub fn variants(&self) -> Vec<Pattern> {
+        let mut variants: Vec<Pattern> = vec![];
+        let mut current = self.clone();
+        for _ in 0..4 {
+            for candidate in [current.clone(), current.reflect()] {
+                if !variants.contains(&candidate) {
+                    variants.push(candidate);
                 }
-            } else {
-                false
             }
-        } else {
-            false
+            current = current.rotate();
         }
-    } else {
-        false
+        variants
     }
+
+    fn matches_at(&self, mat: &Grid, pos: Pos) -> bool {
+        let (px, py) = pos;
+        !self.cells.is_empty()
+            && self.cells.iter().enumerate().all(|(y, row)| {
+                row.iter().enumerate().all(|(x, cell)| {
+                    mat.get(py + y)
+                        .and_then(|v| v.get(px + x))
+                        .is_some_and(|m| cell.is_none_or(|c| c == *m))
+                })
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub pos: Pos,
+    pub variant: Pattern,
 }
 
-fn get_potential_centers(mat: &Grid) -> Vec<Pos> {
+pub fn find_pattern(mat: &Grid, pattern: &Pattern) -> Vec<Placement> {
+    let variants = pattern.variants();
     mat.iter()
         .enumerate()
-        .flat_map(|(y, v)| {
-            v.iter()
-                .enumerate()
-                .filter(|(_, c)| c == &&'A')
-                .map(|(x, _)| (x, y))
-                .collect::<Vec<Pos>>()
+        .flat_map(|(y, v)| (0..v.len()).map(move |x| (x, y)))
+        .flat_map(|pos| {
+            variants
+                .iter()
+                .filter(move |variant| variant.matches_at(mat, pos))
+                .map(move |variant| Placement {
+                    pos,
+                    variant: variant.clone(),
+                })
         })
         .collect()
 }
 
 fn part2(input: &Vec<String>) -> usize {
     let mat = to_matrix(input);
-    let positions = get_potential_centers(&mat);
-    positions
-        .iter()
-        .map(|pos| check_x(&mat, pos))
-        .filter(|b| *b)
-        .count()
+    let x_mas = Pattern::parse(&["M.S", ".A.", "M.S"]);
+    find_pattern(&mat, &x_mas).len()
 }
 
 #[cfg(test)]
@@ -146,7 +215,9 @@ mod tests {
 
     use crate::util::file_io::get_test_input_part;
 
-    use super::{part1, part2};
+    use crate::util::util::to_matrix;
+
+    use super::{Match, Pattern, SearchOptions, find_pattern, part1, part2, search};
 
     #[test]
     fn test_part1() {
@@ -161,4 +232,102 @@ mod tests {
         let res = part2(&input);
         assert_eq!(res, 9);
     }
+
+    #[test]
+    fn test_search() {
+        let input: Vec<String> = ["ABAC", "XBXA", "ACAB"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mat = to_matrix(&input);
+        let options = SearchOptions::default();
+        let res = search(&mat, &["CAB", "BXA"], &options);
+        assert_eq!(
+            res,
+            vec![
+                Match {
+                    start: (3, 0),
+                    direction: (-1, 0),
+                    word: "CAB".to_string(),
+                },
+                Match {
+                    start: (3, 0),
+                    direction: (0, 1),
+                    word: "CAB".to_string(),
+                },
+                Match {
+                    start: (1, 1),
+                    direction: (1, 0),
+                    word: "BXA".to_string(),
+                },
+                Match {
+                    start: (1, 2),
+                    direction: (1, 0),
+                    word: "CAB".to_string(),
+                },
+            ]
+        );
+
+        assert_eq!(search(&mat, &["ABA"], &options).len(), 3);
+        let twice = SearchOptions {
+            palindromes_twice: true,
+            ..options.clone()
+        };
+        assert_eq!(search(&mat, &["ABA"], &twice).len(), 6);
+        assert_eq!(search(&mat, &["AA"], &options).len(), 2);
+        assert_eq!(search(&mat, &["B"], &options).len(), 3);
+
+        let wrap = SearchOptions {
+            wrap: true,
+            ..options
+        };
+        assert_eq!(search(&mat, &["CABA"], &wrap).len(), 6);
+        assert_eq!(search(&mat, &["CABA"], &SearchOptions::default()).len(), 1);
+
+        let ragged = vec![vec!['A'], vec![]];
+        assert_eq!(search(&ragged, &["AB"], &wrap).len(), 0);
+    }
+
+    #[test]
+    fn test_pattern_variants() {
+        let x_mas = Pattern::parse(&["M.S", ".A.", "M.S"]);
+        assert_eq!(x_mas.variants().len(), 4);
+        let plus = Pattern::parse(&[".M.", "MAS", ".S."]);
+        assert_eq!(plus.variants().len(), 4);
+        let corner = Pattern::parse(&["XM", "A."]);
+        assert_eq!(corner.variants().len(), 8);
+        let diagonal = Pattern::parse(&["X...", ".M..", "..A.", "...S"]);
+        assert_eq!(diagonal.variants().len(), 4);
+        let padded = Pattern::parse(&["..", ".X", ".."]);
+        assert_eq!(padded, Pattern::parse(&["X"]));
+        assert_eq!(padded.variants().len(), 1);
+    }
+
+    #[test]
+    fn test_find_pattern() {
+        let input: Vec<String> = ["XMASX", "MAS.M", ".S..A", "....S", "SAMX."]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mat = to_matrix(&input);
+        let plus = Pattern::parse(&[".M.", "MAS", ".S."]);
+        let res = find_pattern(&mat, &plus);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].pos, (0, 0));
+
+        let diagonal = Pattern::parse(&["X...", ".M..", "..A.", "...S"]);
+        assert_eq!(find_pattern(&mat, &diagonal).len(), 0);
+        let line = Pattern::parse(&["XMAS"]);
+        assert_eq!(find_pattern(&mat, &line).len(), 3);
+
+        let row = |s: &str| to_matrix(&vec![s.to_string()]);
+
+        assert_eq!(find_pattern(&row("AX"), &Pattern::parse(&["X."])).len(), 1);
+        let gap = Pattern::parse(&["X.M"]);
+        assert_eq!(find_pattern(&row("XAM"), &gap).len(), 1);
+        assert_eq!(find_pattern(&row("XA"), &gap).len(), 0);
+        let square = to_matrix(&vec!["AB".to_string(), "CD".to_string()]);
+        assert!(find_pattern(&square, &Pattern::parse(&["..", ".."])).is_empty());
+        assert!(find_pattern(&square, &Pattern::parse(&[])).is_empty());
+    }
 }
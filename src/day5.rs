@@ -1,4 +1,8 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fmt::Display,
+};
 
 use crate::{PartFn, puzzle_result::PuzzleResult};
 
@@ -82,59 +86,190 @@ fn part1(input: &Vec<String>) -> usize {
     updates.iter().map(|v| v[v.len() / 2]).sum()
 }
 
-fn out_of_place(key: &usize, to_check: Vec<&usize>, rules: &RuleMap) -> Option<usize> {
-    to_check
-        .iter()
-        .enumerate()
-        .find(|(_, k)| rules.get(k).is_some_and(|v| v.contains(key)))
-        .map(|x| x.0)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CycleError {
+    pub cycle: Vec<usize>,
+}
+
+impl Display for CycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cycle: Vec<String> = self
+            .cycle
+            .iter()
+            .chain(self.cycle.first())
+            .map(|p| p.to_string())
+            .collect();
+        write!(f, "cyclic ordering rules: {}", cycle.join(" -> "))
+    }
+}
+
+fn find_cycle(remaining: &[usize], update: &Update, rules: &RuleMap) -> Vec<usize> {
+    let predecessor = |page: usize| {
+        *remaining
+            .iter()
+            .map(|i| &update[*i])
+            .find(|p| rules.get(p).is_some_and(|v| v.contains(&page)))
+            .unwrap()
+    };
+    let mut path = vec![update[remaining[0]]];
+    loop {
+        let prev = predecessor(*path.last().unwrap());
+        if let Some(i) = path.iter().position(|p| *p == prev) {
+            let mut cycle = path.split_off(i);
+            cycle.reverse();
+            return cycle;
+        }
+        path.push(prev);
+    }
 }
 
-fn fix_update(index: &usize, update: &Update, rules: &RuleMap) -> Option<Update> {
-    let to_correct: Vec<(usize, usize)> = update
+pub fn fix_update(update: &Update, rules: &RuleMap) -> Result<Update, CycleError> {
+    let successors: Vec<Vec<usize>> = update
         .iter()
-        .enumerate()
-        .filter_map(|(i, u)| {
-            let to_check: Vec<&usize> = update.iter().take(i).collect();
-            out_of_place(u, to_check, &rules).map(|j| (i, j))
+        .map(|page| {
+            let targets = rules.get(page);
+            update
+                .iter()
+                .enumerate()
+                .filter(|(_, other)| targets.is_some_and(|v| v.contains(other)))
+                .map(|(j, _)| j)
+                .collect()
         })
         .collect();
-    if to_correct.is_empty() {
-        Some(update.clone())
-    } else {
-        to_correct.iter().find_map(|(i, j)| {
-            let mut u = update.clone();
-            u.swap(*i, *j);
-            if !is_valid(&u, rules) {
-                fix_update(index, &u, rules)
-            } else {
-                Some(u)
+    let mut in_degree = vec![0; update.len()];
+    successors.iter().flatten().for_each(|j| in_degree[*j] += 1);
+
+    let mut ready: BinaryHeap<Reverse<usize>> = (0..update.len())
+        .filter(|i| in_degree[*i] == 0)
+        .map(Reverse)
+        .collect();
+    let mut fixed = Vec::with_capacity(update.len());
+    while let Some(Reverse(i)) = ready.pop() {
+        fixed.push(update[i]);
+        successors[i].iter().for_each(|j| {
+            in_degree[*j] -= 1;
+            if in_degree[*j] == 0 {
+                ready.push(Reverse(*j));
             }
+        });
+    }
+
+    if fixed.len() == update.len() {
+        Ok(fixed)
+    } else {
+        let remaining: Vec<usize> = (0..update.len()).filter(|i| in_degree[*i] > 0).collect();
+        Err(CycleError {
+            cycle: find_cycle(&remaining, update, rules),
         })
     }
 }
 
+fn invalid_updates<'a>(
+    updates: &'a [Update],
+    rules: &'a RuleMap,
+) -> impl Iterator<Item = (&'a Update, Result<Update, CycleError>)> {
+    updates
+        .iter()
+        .filter(|u| !is_valid(u, rules))
+        .map(|u| (u, fix_update(u, rules)))
+}
+
 fn part2(input: &Vec<String>) -> usize {
     let (updates, rules) = prepare(input);
     let rules = vec_to_hashmap(rules);
-    let valid_updates = filter_valid_updates(rules.clone(), updates.clone());
-    let updates: Vec<Update> = updates
+    invalid_updates(&updates, &rules)
+        .filter_map(|(_, fixed)| fixed.ok())
+        .map(|v| v[v.len() / 2])
+        .sum()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub rule: Rule,
+    pub positions: (usize, usize),
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (a, b) = self.rule;
+        let (i, j) = self.positions;
+        write!(f, "{a}|{b} violated: {a} at index {i}, {b} at index {j}")
+    }
+}
+
+pub fn violations(update: &Update, rules: &RuleMap) -> Vec<Violation> {
+    update
         .iter()
-        .filter(|u| !valid_updates.contains(u))
-        .cloned()
+        .enumerate()
+        .flat_map(|(j, b)| {
+            update
+                .iter()
+                .enumerate()
+                .skip(j + 1)
+                .filter(|(_, a)| rules.get(a).is_some_and(|v| v.contains(b)))
+                .map(|(i, a)| Violation {
+                    rule: (*a, *b),
+                    positions: (i, j),
+                })
+                .collect::<Vec<Violation>>()
+        })
+        .collect()
+}
+
+pub fn invalid_update_report(input: &[String]) -> String {
+    let (updates, rules) = prepare(input);
+    let rules = vec_to_hashmap(rules);
+    invalid_updates(&updates, &rules)
+        .map(|(u, fixed)| {
+            let update: Vec<String> = u.iter().map(|p| p.to_string()).collect();
+            let lines: String = violations(u, &rules)
+                .iter()
+                .map(|v| format!("\t{v}\n"))
+                .collect();
+            let cycle = fixed.err().map_or(String::new(), |e| format!("\t{e}\n"));
+            format!("{}\n{lines}{cycle}", update.join(","))
+        })
+        .collect()
+}
+
+fn sorted_edges(rules: &RuleMap) -> Vec<Rule> {
+    let mut edges: Vec<Rule> = rules
+        .iter()
+        .flat_map(|(k, v)| v.iter().map(|v| (*k, *v)))
         .collect();
-    let updates: Vec<Update> = updates
+    edges.sort();
+    edges
+}
+
+pub fn rules_to_dot(rules: &RuleMap) -> String {
+    let edges: String = sorted_edges(rules)
         .iter()
-        .enumerate()
-        .filter_map(|(i, u)| fix_update(&i, u, &rules))
+        .map(|(a, b)| format!("    {a} -> {b};\n"))
         .collect();
-    updates.iter().map(|v| v[v.len() / 2]).sum()
+    format!("digraph rules {{\n{edges}}}\n")
+}
+
+pub fn update_to_dot(update: &Update, rules: &RuleMap) -> String {
+    let position = |page: &usize| update.iter().position(|p| p == page);
+    let nodes: String = update.iter().map(|p| format!("    {p};\n")).collect();
+    let edges: String = sorted_edges(rules)
+        .iter()
+        .filter_map(|(a, b)| match (position(a), position(b)) {
+            (Some(i), Some(j)) if i > j => Some(format!("    {a} -> {b} [color=red];\n")),
+            (Some(_), Some(_)) => Some(format!("    {a} -> {b};\n")),
+            _ => None,
+        })
+        .collect();
+    format!("digraph update {{\n{nodes}{edges}}}\n")
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        day5::{is_valid, vec_to_hashmap},
+        day5::{
+            CycleError, Violation, fix_update, invalid_update_report, is_valid, rules_to_dot,
+            update_to_dot, vec_to_hashmap, violations,
+        },
         util::file_io::get_test_input,
     };
 
@@ -161,4 +296,87 @@ mod tests {
         let (_, rules) = prepare(&input);
         assert!(is_valid(&v, &vec_to_hashmap(rules)))
     }
+
+    #[test]
+    fn test_fix_update() {
+        let input = get_test_input(5);
+        let (_, rules) = prepare(&input);
+        let rules = vec_to_hashmap(rules);
+        assert_eq!(
+            fix_update(&vec![75, 97, 47, 61, 53], &rules),
+            Ok(vec![97, 75, 47, 61, 53])
+        );
+        assert_eq!(
+            fix_update(&vec![97, 13, 75, 29, 47], &rules),
+            Ok(vec![97, 75, 47, 29, 13])
+        );
+    }
+
+    #[test]
+    fn test_fix_update_cycle() {
+        let rules = vec_to_hashmap(vec![(1, 2), (2, 3), (3, 1), (4, 1)]);
+        let res = fix_update(&vec![4, 3, 2, 1], &rules);
+        assert_eq!(
+            res,
+            Err(CycleError {
+                cycle: vec![1, 2, 3]
+            })
+        );
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "cyclic ordering rules: 1 -> 2 -> 3 -> 1"
+        );
+    }
+
+    #[test]
+    fn test_rules_to_dot() {
+        let rules = vec_to_hashmap(vec![(2, 3), (1, 2), (1, 3)]);
+        assert_eq!(
+            rules_to_dot(&rules),
+            "digraph rules {\n    1 -> 2;\n    1 -> 3;\n    2 -> 3;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_update_to_dot() {
+        let rules = vec_to_hashmap(vec![(2, 3), (1, 2), (1, 3), (4, 1)]);
+        assert_eq!(
+            update_to_dot(&vec![2, 1, 3], &rules),
+            "digraph update {\n    2;\n    1;\n    3;\n    1 -> 2 [color=red];\n    1 -> 3;\n    2 -> 3;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_violations() {
+        let input = get_test_input(5);
+        let (_, rules) = prepare(&input);
+        let rules = vec_to_hashmap(rules);
+        assert!(violations(&vec![75, 47, 61, 53, 29], &rules).is_empty());
+        assert_eq!(
+            violations(&vec![61, 13, 29], &rules),
+            vec![Violation {
+                rule: (29, 13),
+                positions: (2, 1),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_update_report() {
+        let input = get_test_input(5);
+        let report = invalid_update_report(&input);
+        assert!(
+            report.starts_with("75,97,47,61,53\n\t97|75 violated: 97 at index 1, 75 at index 0\n")
+        );
+        assert_eq!(report.lines().filter(|l| !l.starts_with('\t')).count(), 3);
+
+        let input: Vec<String> = ["1|2", "2|3", "3|1", "3,2,1", "1,2"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(part2(&input), 0);
+        let report = invalid_update_report(&input);
+        assert_eq!(report.lines().filter(|l| !l.starts_with('\t')).count(), 1);
+        assert!(report.ends_with("\tcyclic ordering rules: 1 -> 2 -> 3 -> 1\n"));
+    }
 }
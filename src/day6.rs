@@ -1,5 +1,7 @@
-use core::panic;
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
 
 use crate::{
     PartFn,
@@ -14,6 +16,29 @@ pub fn day6() -> PuzzleResult<PartFn, PartFn, usize, usize> {
     PuzzleResult::omitted(6, Some(part1), Some(part2))
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rules {
+    pub turn: Turn,
+    pub obstacles: Vec<char>,
+    pub wrap: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            turn: Turn::Right,
+            obstacles: vec!['#'],
+            wrap: false,
+        }
+    }
+}
+
 struct Guard<F: Fn(Pos, Dir, &mut Grid)> {
     x: isize,
     y: isize,
@@ -23,7 +48,7 @@ struct Guard<F: Fn(Pos, Dir, &mut Grid)> {
 
 impl<F: Fn(Pos, Dir, &mut Grid)> Guard<F> {
     fn new(grid: &Grid, replace: F) -> Guard<F> {
-        let (x, y) = Self::get_guard_pos(grid);
+        let (x, y) = *Self::get_guard_positions(grid).first().unwrap();
         let sym = grid[y as usize][x as usize];
         Guard {
             x,
@@ -33,28 +58,41 @@ impl<F: Fn(Pos, Dir, &mut Grid)> Guard<F> {
         }
     }
 
+    fn all(grid: &Grid, replace: F) -> Vec<Guard<F>>
+    where
+        F: Clone,
+    {
+        Self::get_guard_positions(grid)
+            .iter()
+            .map(|(x, y)| {
+                let sym = grid[*y as usize][*x as usize];
+                Guard::from(*x, *y, Self::get_dir(sym), replace.clone())
+            })
+            .collect()
+    }
+
     fn from(x: isize, y: isize, dir: Dir, replace: F) -> Guard<F> {
         Guard { x, y, dir, replace }
     }
 
-    fn step(&mut self, grid: &mut Grid) -> bool {
-        if self.hit_obstacle(grid) {
+    fn step(&mut self, grid: &mut Grid, rules: &Rules) -> bool {
+        if self.hit_obstacle(grid, rules) {
             (self.replace)((self.x, self.y), self.dir, grid);
-            self.rotate();
+            self.rotate(rules.turn);
             (self.replace)((self.x, self.y), self.dir, grid);
             true
-        } else if self.hit_wall(grid) {
+        } else if self.hit_wall(grid, rules) {
             (self.replace)((self.x, self.y), self.dir, grid);
             false
         } else {
-            self.take_step(grid);
+            self.take_step(grid, rules);
             true
         }
     }
 
-    fn take_step(&mut self, grid: &mut Grid) {
+    fn take_step(&mut self, grid: &mut Grid, rules: &Rules) {
         (self.replace)((self.x, self.y), self.dir, grid);
-        let (nx, ny) = self.next_pos();
+        let (nx, ny) = self.next_pos(grid, rules).unwrap();
         self.x = nx;
         self.y = ny;
     }
@@ -71,60 +109,47 @@ impl<F: Fn(Pos, Dir, &mut Grid)> Guard<F> {
         }
     }
 
-    fn rotate(&mut self) {
-        self.dir = (-self.dir.1, self.dir.0);
+    fn rotate(&mut self, turn: Turn) {
+        self.dir = match turn {
+            Turn::Right => (-self.dir.1, self.dir.0),
+            Turn::Left => (self.dir.1, -self.dir.0),
+        };
     }
 
-    fn next_pos(&self) -> Pos {
+    fn next_pos(&self, grid: &Grid, rules: &Rules) -> Option<Pos> {
         let (dx, dy) = self.dir;
-        (self.x + dx, self.y + dy)
-    }
-
-    fn hit_obstacle(&self, grid: &Grid) -> bool {
-        let (nx, ny) = self.next_pos();
+        let (nx, ny) = (self.x + dx, self.y + dy);
         if pos_in_bounds(nx, ny, grid) {
-            let nx = nx as usize;
-            let ny = ny as usize;
-            grid[ny][nx] == '#'
+            Some((nx, ny))
+        } else if rules.wrap {
+            let ny = ny.rem_euclid(grid.len() as isize);
+            let width = grid[ny as usize].len() as isize;
+            (width > 0).then(|| (nx.rem_euclid(width), ny))
         } else {
-            false
+            None
         }
     }
 
-    fn hit_wall(&self, grid: &Grid) -> bool {
-        let (nx, ny) = self.next_pos();
-        !pos_in_bounds(nx, ny, grid)
+    fn hit_obstacle(&self, grid: &Grid, rules: &Rules) -> bool {
+        self.next_pos(grid, rules)
+            .is_some_and(|(nx, ny)| rules.obstacles.contains(&grid[ny as usize][nx as usize]))
     }
 
-    fn get_guard_pos(grid: &Grid) -> Pos {
-        *grid
-            .iter()
+    fn hit_wall(&self, grid: &Grid, rules: &Rules) -> bool {
+        self.next_pos(grid, rules).is_none()
+    }
+
+    fn get_guard_positions(grid: &Grid) -> Vec<Pos> {
+        grid.iter()
             .enumerate()
-            .filter_map(|(y, v)| {
+            .flat_map(|(y, v)| {
                 v.iter()
                     .enumerate()
                     .filter(|(_, c)| is_guard(c))
                     .map(|(x, _)| (x as isize, y as isize))
                     .collect::<Vec<(isize, isize)>>()
-                    .first()
-                    .copied()
             })
-            .collect::<Vec<(isize, isize)>>()
-            .first()
-            .unwrap()
-    }
-    fn get_symbol(dir: Dir) -> char {
-        match dir {
-            (-1, 0) => '<',
-            (0, -1) => '^',
-            (1, 0) => '>',
-            (0, 1) => 'v',
-            _ => {
-                let s = format!("Unexpected dir: {} {}", dir.0, dir.1);
-                eprintln!("{}", s);
-                panic!()
-            }
-        }
+            .collect()
     }
 }
 fn is_guard(c: &char) -> bool {
@@ -135,24 +160,72 @@ fn pos_in_bounds(x: isize, y: isize, grid: &Grid) -> bool {
     y >= 0 && (y as usize) < grid.len() && x >= 0 && (x as usize) < grid[y as usize].len()
 }
 
+#[derive(Debug, Clone)]
+pub struct Patrol {
+    pub visited: HashSet<Pos>,
+    pub looping_guards: Vec<Pos>,
+}
+
+pub fn patrol(grid: &Grid, rules: &Rules) -> Patrol {
+    let mut grid = grid.clone();
+    let replace = |_: Pos, _: Dir, _: &mut Grid| {};
+    let mut guards = Guard::all(&grid, replace);
+    let starts: Vec<Pos> = guards.iter().map(|g| (g.x, g.y)).collect();
+    let mut active = vec![true; guards.len()];
+    let mut seen = vec![HashSet::<(isize, isize, Dir)>::new(); guards.len()];
+    let mut visited: HashSet<Pos> = starts.iter().copied().collect();
+    let mut looping_guards = vec![];
+
+    while active.iter().any(|a| *a) {
+        for (i, guard) in guards.iter_mut().enumerate() {
+            if !active[i] {
+                continue;
+            }
+            if !guard.step(&mut grid, rules) {
+                active[i] = false;
+                continue;
+            }
+            visited.insert((guard.x, guard.y));
+            if !seen[i].insert((guard.x, guard.y, guard.dir)) {
+                active[i] = false;
+                looping_guards.push(starts[i]);
+            }
+        }
+    }
+
+    Patrol {
+        visited,
+        looping_guards,
+    }
+}
+
 fn part1(input: &Vec<String>) -> usize {
-    let mut grid = to_matrix(input);
-    let replace = |pos: Pos, _dir: Dir, grid: &mut Grid| {
-        grid[pos.1 as usize][pos.0 as usize] = 'X';
-    };
-    let mut guard = Guard::new(&grid, replace);
-    while guard.step(&mut grid) {
-        let mut grid = grid.clone();
-        grid[guard.y as usize][guard.x as usize] =
-            Guard::<fn(Pos, Dir, &mut Grid)>::get_symbol(guard.dir);
+    let grid = to_matrix(input);
+    patrol(&grid, &Rules::default()).visited.len()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopObstruction {
+    pub obstruction: Pos,
+    pub loop_length: usize,
+    pub closing_cell: Pos,
+}
+
+impl Display for LoopObstruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "obstruction ({}, {}): loop length {}, closes at ({}, {})",
+            self.obstruction.0,
+            self.obstruction.1,
+            self.loop_length,
+            self.closing_cell.0,
+            self.closing_cell.1
+        )
     }
-    grid.iter()
-        .map(|v| v.iter().filter(|c| c == &&'X').count())
-        .sum()
 }
 
-fn part2(input: &Vec<String>) -> usize {
-    let mut grid = to_matrix(&input.clone());
+fn part2_replace(pos: Pos, dir: Dir, grid: &mut Grid) {
     let get_trace = |dir: Dir| match dir {
         (1, 0) => '>',
         (-1, 0) => '<',
@@ -161,57 +234,82 @@ fn part2(input: &Vec<String>) -> usize {
         _ => panic!("Unexpected direction"),
     };
     let perpendicular = |existing: char, trace: char| {
-        if ((existing == '^' || existing == 'v') && (trace == '<' || trace == '>'))
+        ((existing == '^' || existing == 'v') && (trace == '<' || trace == '>'))
             || ((trace == '^' || trace == 'v') && (existing == '<' || existing == '>'))
-        {
-            true
-        } else {
-            false
-        }
     };
-    let replace = |pos: Pos, dir: Dir, grid: &mut Grid| {
-        let existing = grid[pos.1 as usize][pos.0 as usize];
-        let trace = get_trace(dir);
-        if perpendicular(existing, trace) {
-            grid[pos.1 as usize][pos.0 as usize] = '+';
-        } else {
-            grid[pos.1 as usize][pos.0 as usize] = trace
+    let existing = grid[pos.1 as usize][pos.0 as usize];
+    let trace = get_trace(dir);
+    if perpendicular(existing, trace) {
+        grid[pos.1 as usize][pos.0 as usize] = '+';
+    } else {
+        grid[pos.1 as usize][pos.0 as usize] = trace
+    }
+}
+
+fn detect_loop(
+    input: &[String],
+    obstruction: Pos,
+    initial_pos: Pos,
+    initial_dir: Dir,
+    rules: &Rules,
+) -> Option<LoopObstruction> {
+    let mut grid = to_matrix(&input.to_vec());
+    grid[obstruction.1 as usize][obstruction.0 as usize] = '#';
+    let mut guard = Guard::from(initial_pos.0, initial_pos.1, initial_dir, part2_replace);
+    let mut visited = HashMap::<(isize, isize, Dir), usize>::new();
+    let mut steps = 0;
+    let mut pos = initial_pos;
+    while guard.step(&mut grid, rules) {
+        if (guard.x, guard.y) != pos {
+            pos = (guard.x, guard.y);
+            steps += 1;
         }
-    };
-    let mut guard = Guard::new(&grid, replace);
+        let state = (guard.x, guard.y, guard.dir);
+        if let Some(first_seen) = visited.get(&state) {
+            return Some(LoopObstruction {
+                obstruction,
+                loop_length: steps - first_seen,
+                closing_cell: (guard.x, guard.y),
+            });
+        }
+        visited.insert(state, steps);
+    }
+    None
+}
+
+pub fn find_loop_obstructions(input: &[String]) -> Vec<LoopObstruction> {
+    let rules = Rules::default();
+    let mut grid = to_matrix(&input.to_vec());
+    let mut guard = Guard::new(&grid, part2_replace);
     let initial_pos = (guard.x, guard.y);
     let initial_dir = guard.dir;
     let mut visited = HashSet::<(isize, isize)>::new();
-    while guard.step(&mut grid) {
+    while guard.step(&mut grid, &rules) {
         visited.insert((guard.x, guard.y));
     }
+    visited.remove(&initial_pos);
 
-    visited
+    let mut candidates: Vec<Pos> = visited.into_iter().collect();
+    candidates.sort_by_key(|(x, y)| (*y, *x));
+    candidates
         .iter()
-        .map(|(x, y)| {
-            let mut grid = to_matrix(&input.clone());
-            grid[*y as usize][*x as usize] = '#';
-            let mut guard = Guard::from(initial_pos.0, initial_pos.1, initial_dir, replace);
-            let mut visited = HashSet::<(isize, isize, Dir)>::new();
-            while guard.step(&mut grid) {
-                let state = (guard.x, guard.y, guard.dir);
-                if visited.contains(&state) {
-                    return true;
-                } else {
-                    visited.insert(state);
-                }
-            }
-            false
+        .filter_map(|obstruction| {
+            detect_loop(input, *obstruction, initial_pos, initial_dir, &rules)
         })
-        .filter(|b| *b)
-        .count()
+        .collect()
+}
+
+fn part2(input: &Vec<String>) -> usize {
+    find_loop_obstructions(input).len()
 }
 
 #[cfg(test)]
 mod tests {
     use crate::util::file_io::get_test_input;
 
-    use super::{part1, part2};
+    use crate::util::util::to_matrix;
+
+    use super::{Rules, Turn, find_loop_obstructions, part1, part2, patrol};
 
     #[test]
     fn test_part1() {
@@ -226,4 +324,67 @@ mod tests {
         let res = part2(&input);
         assert_eq!(res, 6);
     }
+
+    #[test]
+    fn test_find_loop_obstructions() {
+        let input = get_test_input(6);
+        let res = find_loop_obstructions(&input);
+        let positions: Vec<(isize, isize)> = res.iter().map(|o| o.obstruction).collect();
+        assert_eq!(
+            positions,
+            vec![(3, 6), (6, 7), (7, 7), (1, 8), (3, 8), (7, 9)]
+        );
+        assert_eq!(res[0].loop_length, 18);
+        assert_eq!(res[0].closing_cell, (4, 5));
+    }
+
+    #[test]
+    fn test_patrol_turn_left() {
+        let input: Vec<String> = ["#....", "....#", "^....", "....."]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let rules = Rules {
+            turn: Turn::Left,
+            ..Rules::default()
+        };
+        let res = patrol(&to_matrix(&input), &rules);
+        assert_eq!(res.visited.len(), 2);
+        assert!(res.looping_guards.is_empty());
+    }
+
+    #[test]
+    fn test_patrol_multiple_guards_and_obstacles() {
+        let input: Vec<String> = ["..O..", "....<", "..^..", "....."]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let rules = Rules {
+            obstacles: vec!['#', 'O'],
+            ..Rules::default()
+        };
+        let res = patrol(&to_matrix(&input), &rules);
+        assert_eq!(res.visited.len(), 6);
+        assert!(res.looping_guards.is_empty());
+    }
+
+    #[test]
+    fn test_patrol_wrap() {
+        let input: Vec<String> = ["...", ">..", "..."]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let rules = Rules {
+            wrap: true,
+            ..Rules::default()
+        };
+        let res = patrol(&to_matrix(&input), &rules);
+        assert_eq!(res.visited.len(), 3);
+        assert_eq!(res.looping_guards, vec![(0, 1)]);
+
+        let input: Vec<String> = [".v.", ""].iter().map(|s| s.to_string()).collect();
+        let res = patrol(&to_matrix(&input), &rules);
+        assert_eq!(res.visited.len(), 1);
+        assert!(res.looping_guards.is_empty());
+    }
 }
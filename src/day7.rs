@@ -1,7 +1,11 @@
+use std::fmt::Display;
+
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+
 use crate::{PartFn, puzzle_result::PuzzleResult};
 
 pub fn day7() -> PuzzleResult<PartFn, PartFn, usize, usize> {
-    PuzzleResult::omitted(7, Some(part1), Some(part2))
+    PuzzleResult::new(7, Some(part1), Some(part2))
 }
 
 fn prepare(input: &[String]) -> Vec<(usize, Vec<usize>)> {
@@ -21,90 +25,269 @@ fn prepare(input: &[String]) -> Vec<(usize, Vec<usize>)> {
         .collect()
 }
 
-fn add(a: usize, b: usize) -> usize {
-    a + b
+fn digits_pow<const BASE: usize>(b: usize) -> Option<usize> {
+    let mut pow = BASE;
+    while pow <= b {
+        pow = pow.checked_mul(BASE)?;
+    }
+    Some(pow)
+}
+
+pub enum Prefix {
+    Exactly(usize),
+    Any,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Operator {
+    pub symbol: &'static str,
+    pub apply: fn(usize, usize) -> Option<usize>,
+    pub inverse: Option<fn(usize, usize) -> Option<Prefix>>,
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.symbol)
+    }
+}
+
+fn concat<const BASE: usize>(a: usize, b: usize) -> Option<usize> {
+    a.checked_mul(digits_pow::<BASE>(b)?)?.checked_add(b)
 }
-fn mul(a: usize, b: usize) -> usize {
-    a * b
+
+fn strip_suffix<const BASE: usize>(target: usize, suffix: usize) -> Option<Prefix> {
+    let pow = digits_pow::<BASE>(suffix)?;
+    (target % pow == suffix).then_some(Prefix::Exactly(target / pow))
 }
 
-fn try_solve(
-    (result, operands): &(usize, Vec<usize>),
-    operators: Vec<fn(usize, usize) -> usize>,
+pub const ADD: Operator = Operator {
+    symbol: "+",
+    apply: |a, b| a.checked_add(b),
+    inverse: Some(|target, last| target.checked_sub(last).map(Prefix::Exactly)),
+};
+
+pub const MUL: Operator = Operator {
+    symbol: "*",
+    apply: |a, b| a.checked_mul(b),
+    inverse: Some(|target, last| match last {
+        0 => (target == 0).then_some(Prefix::Any),
+        last => target
+            .is_multiple_of(last)
+            .then(|| Prefix::Exactly(target / last)),
+    }),
+};
+
+pub const SUB: Operator = Operator {
+    symbol: "-",
+    apply: |a, b| a.checked_sub(b),
+    inverse: Some(|target, last| target.checked_add(last).map(Prefix::Exactly)),
+};
+
+pub const XOR: Operator = Operator {
+    symbol: "^",
+    apply: |a, b| Some(a ^ b),
+    inverse: Some(|target, last| Some(Prefix::Exactly(target ^ last))),
+};
+
+pub const CONCAT: Operator = Operator {
+    symbol: "||",
+    apply: concat::<10>,
+    inverse: Some(strip_suffix::<10>),
+};
+
+pub const CONCAT_BIN: Operator = Operator {
+    symbol: "||b",
+    apply: concat::<2>,
+    inverse: Some(strip_suffix::<2>),
+};
+
+pub const CONCAT_HEX: Operator = Operator {
+    symbol: "||x",
+    apply: concat::<16>,
+    inverse: Some(strip_suffix::<16>),
+};
+
+pub const PART1_OPERATORS: [Operator; 2] = [ADD, MUL];
+pub const PART2_OPERATORS: [Operator; 3] = [ADD, MUL, CONCAT];
+
+fn find_forward(
+    acc: usize,
+    operands: &[usize],
+    ops: &[Operator],
+    witness: &mut Vec<Operator>,
+    accept: &dyn Fn(usize) -> bool,
 ) -> bool {
-    if operators.len() == operands.len() - 1 {
-        let mut a = operands[0];
-        for i in 1..operands.len() {
-            let b = operands[i];
-            a = (operators[i - 1])(a, b);
+    let Some((next, rest)) = operands.split_first() else {
+        return accept(acc);
+    };
+    for op in ops {
+        if let Some(acc) = (op.apply)(acc, *next) {
+            witness.push(*op);
+            if find_forward(acc, rest, ops, witness, accept) {
+                return true;
+            }
+            witness.pop();
         }
-        a == *result
-    } else {
-        try_solve(
-            &(*result, operands.clone()),
-            [operators.clone(), vec![add]].concat(),
-        ) || try_solve(
-            &(*result, operands.clone()),
-            [operators, vec![mul]].concat(),
-        )
     }
+    false
 }
 
-fn part1(input: &Vec<String>) -> usize {
-    let equations = prepare(input);
-    equations
-        .iter()
-        .filter(|e| try_solve(e, vec![]))
-        .map(|(r, _)| r)
+fn count_forward(
+    acc: usize,
+    operands: &[usize],
+    ops: &[Operator],
+    accept: &dyn Fn(usize) -> bool,
+) -> usize {
+    let Some((next, rest)) = operands.split_first() else {
+        return accept(acc) as usize;
+    };
+    ops.iter()
+        .filter_map(|op| (op.apply)(acc, *next))
+        .map(|acc| count_forward(acc, rest, ops, accept))
         .sum()
 }
 
-fn concatination(a: usize, b: usize) -> usize {
-    let mut a = a.to_string();
-    let b = b.to_string();
-    a.push_str(b.as_str());
-    a.parse::<usize>().unwrap()
-}
-
-fn try_solve2(
-    (result, operands): &(usize, Vec<usize>),
-    operators: Vec<fn(usize, usize) -> usize>,
+fn find_witness(
+    target: usize,
+    operands: &[usize],
+    ops: &[Operator],
+    witness: &mut Vec<Operator>,
 ) -> bool {
-    if operators.len() == operands.len() - 1 {
-        let mut a = operands[0];
-        for i in 1..operands.len() {
-            let b = operands[i];
-            a = (operators[i - 1])(a, b);
+    let Some((last, rest)) = operands.split_last() else {
+        return false;
+    };
+    if rest.is_empty() {
+        return target == *last;
+    }
+    for op in ops {
+        witness.push(*op);
+        let start = witness.len();
+        let backward = |t: usize, witness: &mut Vec<Operator>| find_witness(t, rest, ops, witness);
+        let forward = |accept: &dyn Fn(usize) -> bool, witness: &mut Vec<Operator>| {
+            let found = find_forward(rest[0], &rest[1..], ops, witness, accept);
+            witness[start..].reverse();
+            found
+        };
+        let found = match op.inverse.map(|inverse| inverse(target, *last)) {
+            Some(Some(Prefix::Exactly(t))) => backward(t, witness),
+            Some(Some(Prefix::Any)) => forward(&|_| true, witness),
+            Some(None) => false,
+            None => forward(&|acc| (op.apply)(acc, *last) == Some(target), witness),
+        };
+        if found {
+            return true;
         }
-        a == *result
-    } else {
-        try_solve2(
-            &(*result, operands.clone()),
-            [operators.clone(), vec![add]].concat(),
-        ) || try_solve2(
-            &(*result, operands.clone()),
-            [operators.clone(), vec![mul]].concat(),
-        ) || try_solve2(
-            &(*result, operands.clone()),
-            [operators, vec![concatination]].concat(),
+        witness.pop();
+    }
+    false
+}
+
+fn count_assignments(target: usize, operands: &[usize], ops: &[Operator]) -> usize {
+    let Some((last, rest)) = operands.split_last() else {
+        return 0;
+    };
+    if rest.is_empty() {
+        return (target == *last) as usize;
+    }
+    ops.iter()
+        .map(
+            |op| match op.inverse.map(|inverse| inverse(target, *last)) {
+                Some(Some(Prefix::Exactly(t))) => count_assignments(t, rest, ops),
+                Some(Some(Prefix::Any)) => count_forward(rest[0], &rest[1..], ops, &|_| true),
+                Some(None) => 0,
+                None => count_forward(rest[0], &rest[1..], ops, &|acc| {
+                    (op.apply)(acc, *last) == Some(target)
+                }),
+            },
         )
+        .sum()
+}
+
+fn witness(target: usize, operands: &[usize], ops: &[Operator]) -> Option<Vec<Operator>> {
+    let mut witness = Vec::with_capacity(operands.len());
+    if find_witness(target, operands, ops, &mut witness) {
+        witness.reverse();
+        Some(witness)
+    } else {
+        None
     }
 }
 
-fn part2(input: &Vec<String>) -> usize {
+fn solvable(target: usize, operands: &[usize], ops: &[Operator]) -> bool {
+    witness(target, operands, ops).is_some()
+}
+
+#[derive(Debug, Clone)]
+pub struct Calibration {
+    pub result: usize,
+    pub operands: Vec<usize>,
+    pub witness: Option<Vec<Operator>>,
+    pub assignments: Option<usize>,
+}
+
+impl Calibration {
+    pub fn expression(&self) -> Option<String> {
+        self.witness.as_ref().map(|ops| {
+            ops.iter()
+                .zip(self.operands.iter().skip(1))
+                .fold(self.operands[0].to_string(), |acc, (op, b)| {
+                    format!("{acc} {op} {b}")
+                })
+        })
+    }
+}
+
+impl Display for Calibration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.expression() {
+            Some(expression) => write!(f, "{}: {}", self.result, expression)?,
+            None => write!(f, "{}: unsolvable", self.result)?,
+        }
+        if let Some(assignments) = self.assignments {
+            write!(f, " ({assignments} assignments)")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn calibrations(input: &[String], ops: &[Operator], count: bool) -> Vec<Calibration> {
     let equations = prepare(input);
     equations
-        .iter()
-        .filter(|e| try_solve2(e, vec![]))
+        .into_par_iter()
+        .map(|(result, operands)| Calibration {
+            result,
+            witness: witness(result, &operands, ops),
+            assignments: count.then(|| count_assignments(result, &operands, ops)),
+            operands,
+        })
+        .collect()
+}
+
+pub fn calibrate(input: &[String], ops: &[Operator]) -> usize {
+    let equations = prepare(input);
+    equations
+        .par_iter()
+        .filter(|(result, operands)| solvable(*result, operands, ops))
         .map(|(r, _)| r)
         .sum()
 }
 
+fn part1(input: &Vec<String>) -> usize {
+    calibrate(input, &PART1_OPERATORS)
+}
+
+fn part2(input: &Vec<String>) -> usize {
+    calibrate(input, &PART2_OPERATORS)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::util::file_io::get_test_input;
 
-    use super::{part1, part2};
+    use super::{
+        ADD, CONCAT, CONCAT_BIN, CONCAT_HEX, MUL, Operator, PART1_OPERATORS, PART2_OPERATORS, SUB,
+        XOR, calibrations, part1, part2, solvable, witness,
+    };
 
     #[test]
     fn test_part1() {
@@ -119,4 +302,75 @@ mod tests {
         let res = part2(&input);
         assert_eq!(res, 11387);
     }
+
+    #[test]
+    fn test_solvable() {
+        assert!(solvable(190, &[10, 19], &PART1_OPERATORS));
+        assert!(solvable(3267, &[81, 40, 27], &PART1_OPERATORS));
+        assert!(!solvable(156, &[15, 6], &PART1_OPERATORS));
+        assert!(solvable(156, &[15, 6], &PART2_OPERATORS));
+        assert!(solvable(7290, &[6, 8, 6, 15], &PART2_OPERATORS));
+        assert!(!solvable(161011, &[16, 10, 13], &PART2_OPERATORS));
+        assert!(solvable(0, &[5, 0], &PART1_OPERATORS));
+    }
+
+    #[test]
+    fn test_calibrations() {
+        let input = get_test_input(7);
+        let res: Vec<String> = calibrations(&input, &PART1_OPERATORS, true)
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(res[0], "190: 10 * 19 (1 assignments)");
+        assert_eq!(res[1], "3267: 81 * 40 + 27 (2 assignments)");
+        assert_eq!(res[2], "83: unsolvable (0 assignments)");
+
+        let res = calibrations(&input, &PART2_OPERATORS, false);
+        assert_eq!(res[3].expression(), Some("15 || 6".to_string()));
+        assert_eq!(res[4].to_string(), "7290: 6 * 8 || 6 * 15");
+    }
+
+    #[test]
+    fn test_custom_operators() {
+        assert!(solvable(5, &[10, 3, 2], &[SUB]));
+        assert!(!solvable(5, &[3, 10, 2], &[SUB, ADD]));
+        assert!(solvable(6, &[5, 3], &[XOR]));
+        assert!(solvable(0b1011, &[0b10, 0b11], &[CONCAT_BIN]));
+        assert!(solvable(0xab, &[0xa, 0xb], &[ADD, CONCAT_HEX]));
+        assert!(!solvable(0xab, &[0xa, 0xb], &[ADD, MUL]));
+    }
+
+    #[test]
+    fn test_operator_without_inverse() {
+        const MAX: Operator = Operator {
+            symbol: "max",
+            apply: |a, b| Some(a.max(b)),
+            inverse: None,
+        };
+        let ops = [ADD, MAX, MUL];
+        let res = witness(100, &[3, 7, 2, 10], &ops).unwrap();
+        let res: Vec<&str> = res.iter().map(|op| op.symbol).collect();
+        assert_eq!(res, vec!["+", "max", "*"]);
+        assert!(!solvable(1000, &[3, 7, 2], &ops));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let big = usize::MAX / 2 + 1;
+        assert!(!solvable(usize::MAX, &[big, 2, 1], &[ADD, MUL]));
+        assert!(solvable(usize::MAX, &[big, big - 1], &[ADD, MUL]));
+        assert!(!solvable(11, &[usize::MAX, 1, 11], &[ADD, CONCAT]));
+        assert!(!solvable(1, &[1, usize::MAX, 2], &[SUB, CONCAT]));
+        assert!(!solvable(0, &[usize::MAX, 2, 0], &[ADD, MUL]));
+        assert!(!solvable(usize::MAX, &[0, usize::MAX], &[CONCAT]));
+        assert!(!solvable(usize::MAX, &[1, usize::MAX], &[CONCAT]));
+
+        const ID: Operator = Operator {
+            symbol: "id",
+            apply: |a, _| Some(a),
+            inverse: None,
+        };
+        assert!(!solvable(usize::MAX, &[0, usize::MAX, 5], &[CONCAT, ID]));
+        assert!(solvable(7, &[0, 7, 5], &[CONCAT, ID]));
+    }
 }
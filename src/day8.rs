@@ -1,5 +1,5 @@
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     fmt::Display,
     ops::{Add, Sub},
     vec,
@@ -16,7 +16,7 @@ pub fn day8() -> PuzzleResult<PartFn, PartFn, usize, usize> {
 }
 
 type Pos = (usize, usize);
-type Group = (char, Vec<Pos>);
+pub type Group = (char, Vec<Pos>);
 
 #[derive(Clone, PartialEq)]
 struct Vec2 {
@@ -74,7 +74,7 @@ impl Display for Vec2 {
     }
 }
 
-fn group(antenna: &char, grid: &Grid) -> Group {
+pub fn group(antenna: &char, grid: &Grid) -> Group {
     let filter_map = |(y, v): (usize, &Vec<char>)| {
         v.iter()
             .enumerate()
@@ -87,24 +87,97 @@ fn group(antenna: &char, grid: &Grid) -> Group {
     (*antenna, group)
 }
 
-fn create_antinode(group: &Group, grid: &Grid) -> Vec<Pos> {
-    let (antenna, positions) = group;
+#[derive(Debug, Clone, PartialEq)]
+pub struct Antennas {
+    pub frequencies: BTreeMap<char, Vec<Pos>>,
+}
+
+impl Antennas {
+    pub fn scan(grid: &Grid) -> Antennas {
+        let mut frequencies: BTreeMap<char, Vec<Pos>> = BTreeMap::new();
+        grid.iter().enumerate().for_each(|(y, v)| {
+            v.iter()
+                .enumerate()
+                .filter(|(_, c)| c != &&'.')
+                .for_each(|(x, c)| frequencies.entry(*c).or_default().push((x, y)))
+        });
+        Antennas { frequencies }
+    }
+
+    pub fn groups(&self) -> Vec<Group> {
+        self.frequencies
+            .iter()
+            .map(|(c, positions)| (*c, positions.clone()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Harmonics {
+    Multiples(Vec<isize>),
+    AllMultiples,
+    Lattice,
+}
+
+fn gcd(a: isize, b: isize) -> isize {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+fn walk(
+    origin: &Vec2,
+    direction: &Vec2,
+    factors: impl Iterator<Item = isize>,
+    grid: &Grid,
+) -> Vec<Pos> {
+    factors
+        .map(|k| origin.clone() + direction.mul(k))
+        .take_while(|v| vec_in_bounds(v, grid))
+        .filter_map(|v| v.to_pos())
+        .collect()
+}
+
+pub fn antinodes(group: &Group, grid: &Grid, harmonics: &Harmonics) -> Vec<Pos> {
+    let (_, positions) = group;
     positions
         .iter()
         .flat_map(|pos| {
-            let others: Vec<&Pos> = positions.iter().filter(|p| p != &pos).collect();
-            others
+            positions
                 .iter()
-                .map(|p| Vec2::from(p) - Vec2::from(pos))
-                .map(|v| Vec2::from(pos) + v.mul(2))
-                .filter(|v| vec_in_bounds(v, grid))
-                .filter_map(|v| v.to_pos())
+                .filter(|p| p != &pos)
+                .flat_map(|p| {
+                    let origin = Vec2::from(pos);
+                    let direction = Vec2::from(p) - Vec2::from(pos);
+                    match harmonics {
+                        Harmonics::Multiples(factors) => factors
+                            .iter()
+                            .map(|k| origin.clone() + direction.mul(*k))
+                            .filter(|v| vec_in_bounds(v, grid))
+                            .filter_map(|v| v.to_pos())
+                            .collect(),
+                        Harmonics::AllMultiples => walk(&origin, &direction, 0.., grid),
+                        Harmonics::Lattice => {
+                            let divisor = gcd(direction.x, direction.y);
+                            let step = Vec2 {
+                                x: direction.x / divisor,
+                                y: direction.y / divisor,
+                            };
+                            [
+                                walk(&origin, &step, 0.., grid),
+                                walk(&origin, &step, (1..).map(|k: isize| -k), grid),
+                            ]
+                            .concat()
+                        }
+                    }
+                })
                 .collect::<Vec<Pos>>()
         })
-        .filter(|v| &grid[v.1][v.0] != antenna)
         .collect()
 }
 
+fn create_antinode(group: &Group, grid: &Grid) -> Vec<Pos> {
+    antinodes(group, grid, &Harmonics::Multiples(vec![2]))
+}
+
 fn vec_in_bounds(v: &Vec2, grid: &Grid) -> bool {
     if let Some(pos) = v.to_pos() {
         pos_in_bounds(&pos, grid)
@@ -120,16 +193,7 @@ fn pos_in_bounds(pos: &Pos, grid: &Grid) -> bool {
 
 fn part1(input: &Vec<String>) -> usize {
     let map = to_matrix(input);
-    let antennas: Vec<char> = map
-        .iter()
-        .flat_map(|v| {
-            v.iter()
-                .filter(|c| c != &&'.')
-                .copied()
-                .collect::<Vec<char>>()
-        })
-        .collect();
-    let groups: HashSet<Group> = antennas.iter().map(|c| group(c, &map)).collect();
+    let groups = Antennas::scan(&map).groups();
     groups
         .iter()
         .flat_map(|g| create_antinode(g, &map))
@@ -137,58 +201,57 @@ fn part1(input: &Vec<String>) -> usize {
         .len()
 }
 
-fn continouous(origin: Vec2, direction: Vec2, grid: &Grid) -> Vec<Pos> {
-    let mut res = vec![];
-    let mut factor = 1;
-    let mut antenna = origin.clone() + direction.mul(factor);
-    while vec_in_bounds(&antenna, grid) {
-        res.push(antenna.to_pos().unwrap());
-        factor += 1;
-        antenna = origin.clone() + direction.mul(factor);
-    }
-    res
-}
-
 fn create_antinode2(group: &Group, grid: &Grid) -> Vec<Pos> {
-    let (_, positions) = group;
-    positions
-        .iter()
-        .flat_map(|pos| {
-            let others: Vec<&Pos> = positions.iter().filter(|p| p != &pos).collect();
-            let res = others
-                .iter()
-                .map(|p| Vec2::from(p) - Vec2::from(pos))
-                .flat_map(|v| continouous(Vec2::from(pos), v, grid))
-                .collect::<Vec<Pos>>();
-            [res, vec![*pos]].concat()
-        })
-        // .filter(|v| &grid[v.1][v.0] != antenna)
-        .collect()
+    antinodes(group, grid, &Harmonics::AllMultiples)
 }
 
 fn part2(input: &Vec<String>) -> usize {
     let map = to_matrix(input);
-    let antennas: Vec<char> = map
-        .iter()
-        .flat_map(|v| {
-            v.iter()
-                .filter(|c| c != &&'.')
-                .copied()
-                .collect::<Vec<char>>()
-        })
-        .collect();
-    let groups: HashSet<Group> = antennas.iter().map(|c| group(c, &map)).collect();
+    let groups = Antennas::scan(&map).groups();
     groups
         .iter()
         .flat_map(|g| create_antinode2(g, &map))
         .collect::<HashSet<Pos>>()
         .len()
 }
+
+pub fn render_antinodes(grid: &Grid, harmonics: &Harmonics, frequency: Option<char>) -> String {
+    let groups = match frequency {
+        Some(c) => vec![group(&c, grid)],
+        None => Antennas::scan(grid).groups(),
+    };
+    let nodes: HashSet<Pos> = groups
+        .iter()
+        .flat_map(|g| antinodes(g, grid, harmonics))
+        .collect();
+    grid.iter()
+        .enumerate()
+        .map(|(y, v)| {
+            v.iter()
+                .enumerate()
+                .map(|(x, c)| {
+                    let shown = frequency.is_none_or(|f| f == *c);
+                    match (shown && c != &'.', nodes.contains(&(x, y))) {
+                        (true, _) => *c,
+                        (false, true) => '#',
+                        (false, false) => '.',
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
 #[cfg(test)]
 mod tests {
     use crate::util::{file_io::get_test_input, util::to_matrix};
 
-    use super::{create_antinode, group, part1, part2};
+    use std::collections::HashSet;
+
+    use super::{
+        Antennas, Harmonics, antinodes, create_antinode, group, part1, part2, render_antinodes,
+    };
 
     #[test]
     fn test_part1() {
@@ -222,6 +285,14 @@ mod tests {
         assert_eq!(res.1.len(), 4);
         let res = group(&'A', &grid);
         assert_eq!(res.1.len(), 3);
+
+        let antennas = Antennas::scan(&grid);
+        assert_eq!(
+            antennas.frequencies.keys().collect::<Vec<&char>>(),
+            vec![&'0', &'A']
+        );
+        assert_eq!(antennas.groups()[0], group(&'0', &grid));
+        assert_eq!(antennas.groups()[1], group(&'A', &grid));
     }
 
     #[test]
@@ -259,4 +330,82 @@ mod tests {
         let res = part2(&input);
         assert_eq!(res, 34);
     }
+
+    #[test]
+    fn test_harmonics() {
+        let input = [".....", ".a...", ".....", "...a.", "....."]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        let grid = to_matrix(&input);
+        let g = group(&'a', &grid);
+        let unique = |harmonics: Harmonics| {
+            antinodes(&g, &grid, &harmonics)
+                .into_iter()
+                .collect::<HashSet<(usize, usize)>>()
+                .len()
+        };
+        assert_eq!(unique(Harmonics::Multiples(vec![2])), 0);
+        assert_eq!(unique(Harmonics::Multiples(vec![0, 1])), 2);
+        assert_eq!(unique(Harmonics::AllMultiples), 2);
+        assert_eq!(unique(Harmonics::Lattice), 5);
+    }
+
+    #[test]
+    fn test_render_antinodes() {
+        let input = get_test_input(8);
+        let grid = to_matrix(&input);
+        let expected = [
+            "......#....#",
+            "...#....0...",
+            "....#0....#.",
+            "..#....0....",
+            "....0....#..",
+            ".#....A.....",
+            "...#........",
+            "#......#....",
+            "........A...",
+            ".........A..",
+            "..........#.",
+            "..........#.",
+        ];
+        assert_eq!(
+            render_antinodes(&grid, &Harmonics::Multiples(vec![2]), None),
+            expected.join("\n")
+        );
+
+        let grid = to_matrix(
+            &[
+                "T.........",
+                "...T......",
+                ".T........",
+                "..........",
+                "..........",
+                "..........",
+                "..........",
+                "..........",
+                "..........",
+                "..........",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>(),
+        );
+        let expected = [
+            "T....#....",
+            "...T......",
+            ".T....#...",
+            ".........#",
+            "..#.......",
+            "..........",
+            "...#......",
+            "..........",
+            "....#.....",
+            "..........",
+        ];
+        assert_eq!(
+            render_antinodes(&grid, &Harmonics::AllMultiples, Some('T')),
+            expected.join("\n")
+        );
+    }
 }
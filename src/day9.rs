@@ -1,7 +1,9 @@
+use std::{cmp::Reverse, collections::BinaryHeap, fmt::Display};
+
 use crate::{PartFn, puzzle_result::PuzzleResult};
 
 pub fn day9() -> PuzzleResult<PartFn, PartFn, usize, usize> {
-    PuzzleResult::omitted(9, Some(part1), Some(part2))
+    PuzzleResult::new(9, Some(part1), Some(part2))
 }
 
 #[derive(Debug)]
@@ -37,7 +39,7 @@ fn parse_input(input: &str) -> Vec<File> {
     files
 }
 
-fn parse_disk(input: &str) -> Vec<Option<u32>> {
+pub fn parse_disk(input: &str) -> Vec<Option<u32>> {
     let mut is_filename = true;
     let mut filename: u32 = 0;
     let mut disk: Vec<Option<u32>> = Vec::new();
@@ -55,60 +57,285 @@ fn parse_disk(input: &str) -> Vec<Option<u32>> {
     disk
 }
 
-fn part1(input: &Vec<String>) -> usize {
-    let disk = parse_disk(input[0].as_str());
-    let mut compressed_disk = disk.clone();
-    while compressed_disk.contains(&None) {
-        let last = compressed_disk.pop().unwrap();
-        if last.is_none() {
-            continue;
-        }
-        if let Some(first_none) = compressed_disk.iter_mut().find(|x| x.is_none()) {
-            *first_none = last;
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+    id: u32,
+    start: usize,
+    len: usize,
+}
+
+fn parse_free_spans(input: &str) -> Vec<(usize, usize)> {
+    let mut ptr: usize = 0;
+    let mut spans = Vec::new();
+    for (i, c) in input.trim().chars().enumerate() {
+        let length = c.to_digit(10).unwrap() as usize;
+        if i % 2 == 1 && length > 0 {
+            spans.push((ptr, length));
         }
+        ptr += length;
     }
-    compressed_disk
+    spans
+}
+
+fn checksum(segments: &[Segment]) -> usize {
+    segments
         .iter()
-        .enumerate()
-        .map(|(i, file_id)| i * file_id.unwrap() as usize)
+        .map(|s| s.id as usize * (s.start * s.len + s.len * s.len.saturating_sub(1) / 2))
         .sum()
 }
 
-fn part2(input: &Vec<String>) -> usize {
-    let disk_map = parse_input(input[0].as_str());
-    let disk = parse_disk(input[0].as_str());
-    let mut compressed_disk = disk.clone();
-    for file in disk_map.iter().rev() {
-        let right_ptr = file.end;
-        let window_size = file.end - file.start + 1;
-        if let Some(start_idx) = compressed_disk
-            .windows(window_size)
-            .position(|window| window == vec![None; window_size])
-        {
-            if start_idx < right_ptr {
-                for i in start_idx..(start_idx + window_size) {
-                    compressed_disk[i] = Some(file.id);
-                }
-                for i in file.start..=file.end {
-                    compressed_disk[i] = None;
+fn compact_blocks(files: &[File], free: &[(usize, usize)]) -> Vec<Segment> {
+    let mut segments = Vec::with_capacity(files.len() + free.len());
+    let mut free = free.iter().copied();
+    let mut span = free.next();
+    for file in files.iter().rev() {
+        let mut remaining = file.end - file.start + 1;
+        while remaining > 0 {
+            match span {
+                Some((start, len)) if start < file.start => {
+                    let moved = len.min(remaining);
+                    segments.push(Segment {
+                        id: file.id,
+                        start,
+                        len: moved,
+                    });
+                    remaining -= moved;
+                    span = if moved < len {
+                        Some((start + moved, len - moved))
+                    } else {
+                        free.next()
+                    };
                 }
+                _ => break,
             }
         }
+        if remaining > 0 {
+            segments.push(Segment {
+                id: file.id,
+                start: file.start,
+                len: remaining,
+            });
+        }
     }
+    segments
+}
 
-    compressed_disk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    Blocks,
+    FirstFit,
+    BestFit,
+    WorstFit,
+    Defrag,
+}
+
+pub const STRATEGIES: [Strategy; 5] = [
+    Strategy::Blocks,
+    Strategy::FirstFit,
+    Strategy::BestFit,
+    Strategy::WorstFit,
+    Strategy::Defrag,
+];
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Strategy::Blocks => f.write_str("block-level"),
+            Strategy::FirstFit => f.write_str("first-fit"),
+            Strategy::BestFit => f.write_str("best-fit"),
+            Strategy::WorstFit => f.write_str("worst-fit"),
+            Strategy::Defrag => f.write_str("defrag"),
+        }
+    }
+}
+
+fn compact_files(files: &[File], free: &[(usize, usize)], strategy: Strategy) -> Vec<Segment> {
+    let mut heaps: Vec<BinaryHeap<Reverse<usize>>> = vec![BinaryHeap::new(); 10];
+    free.iter()
+        .for_each(|(start, len)| heaps[*len].push(Reverse(*start)));
+    files
         .iter()
-        .enumerate()
-        .filter(|(_, x)| x.is_some())
-        .map(|(i, file_id)| i * file_id.unwrap() as usize)
-        .sum()
+        .rev()
+        .map(|file| {
+            let len = file.end - file.start + 1;
+            let candidates = (len..heaps.len())
+                .filter_map(|size| heaps[size].peek().map(|Reverse(start)| (*start, size)))
+                .filter(|(start, _)| *start < file.start);
+            let best = match strategy {
+                Strategy::BestFit => candidates.min_by_key(|(start, size)| (*size, *start)),
+                Strategy::WorstFit => {
+                    candidates.min_by_key(|(start, size)| (Reverse(*size), *start))
+                }
+                _ => candidates.min(),
+            };
+            let start = if let Some((start, size)) = best {
+                heaps[size].pop();
+                if size > len {
+                    heaps[size - len].push(Reverse(start + len));
+                }
+                start
+            } else {
+                file.start
+            };
+            Segment {
+                id: file.id,
+                start,
+                len,
+            }
+        })
+        .collect()
+}
+
+fn defrag(files: &[File]) -> Vec<Segment> {
+    let mut ptr = 0;
+    files
+        .iter()
+        .map(|file| {
+            let len = file.end - file.start + 1;
+            ptr += len;
+            Segment {
+                id: file.id,
+                start: ptr - len,
+                len,
+            }
+        })
+        .collect()
+}
+
+fn compact(files: &[File], free: &[(usize, usize)], strategy: Strategy) -> Vec<Segment> {
+    match strategy {
+        Strategy::Blocks => compact_blocks(files, free),
+        Strategy::Defrag => defrag(files),
+        _ => compact_files(files, free, strategy),
+    }
+}
+
+pub fn checksums(input: &str) -> Vec<(Strategy, usize)> {
+    let files = parse_input(input);
+    let free = parse_free_spans(input);
+    STRATEGIES
+        .iter()
+        .map(|strategy| (*strategy, checksum(&compact(&files, &free, *strategy))))
+        .collect()
+}
+
+fn segments_to_disk(segments: &[Segment], len: usize) -> Vec<Option<u32>> {
+    let mut disk = vec![None; len];
+    segments.iter().for_each(|s| {
+        disk[s.start..s.start + s.len].fill(Some(s.id));
+    });
+    disk
+}
+
+pub fn compact_disk(input: &str, strategy: Strategy) -> Vec<Option<u32>> {
+    let files = parse_input(input);
+    let free = parse_free_spans(input);
+    let segments = compact(&files, &free, strategy);
+    segments_to_disk(&segments, parse_disk(input).len())
+}
+
+pub fn render_disk(disk: &[Option<u32>], width: usize) -> String {
+    let rendered: String = disk
+        .iter()
+        .take(width)
+        .map(|block| match block {
+            Some(id) => char::from_digit(id % 10, 10).unwrap(),
+            None => '.',
+        })
+        .collect();
+    if disk.len() > width {
+        format!("{rendered}... ({} more blocks)", disk.len() - width)
+    } else {
+        rendered
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fragmentation {
+    pub free_spans: usize,
+    pub largest_free_span: usize,
+    pub files_moved: usize,
+    pub files_in_place: usize,
+}
+
+impl Display for Fragmentation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "free spans: {}, largest free span: {}, files moved: {}, files in place: {}",
+            self.free_spans, self.largest_free_span, self.files_moved, self.files_in_place
+        )
+    }
+}
+
+fn block_positions(disk: &[Option<u32>]) -> Vec<Vec<usize>> {
+    let mut positions: Vec<Vec<usize>> = vec![];
+    disk.iter().enumerate().for_each(|(i, block)| {
+        if let Some(id) = block {
+            let id = *id as usize;
+            if positions.len() <= id {
+                positions.resize(id + 1, vec![]);
+            }
+            positions[id].push(i);
+        }
+    });
+    positions
+}
+
+pub fn fragmentation(before: &[Option<u32>], after: &[Option<u32>]) -> Fragmentation {
+    let used = after.iter().rposition(|b| b.is_some()).map_or(0, |i| i + 1);
+    let spans: Vec<usize> = after[..used]
+        .split(|b| b.is_some())
+        .map(|span| span.len())
+        .filter(|len| *len > 0)
+        .collect();
+    let before = block_positions(before);
+    let after = block_positions(after);
+    let files_moved = before
+        .iter()
+        .zip(after.iter())
+        .filter(|(a, b)| a != b)
+        .count();
+    Fragmentation {
+        free_spans: spans.len(),
+        largest_free_span: spans.iter().copied().max().unwrap_or(0),
+        files_moved,
+        files_in_place: before.len() - files_moved,
+    }
+}
+
+pub fn compaction_report(input: &str, strategy: Strategy, width: usize) -> String {
+    let before = parse_disk(input);
+    let after = compact_disk(input, strategy);
+    format!(
+        "before: {}\nafter:  {}\n{}",
+        render_disk(&before, width),
+        render_disk(&after, width),
+        fragmentation(&before, &after)
+    )
+}
+
+fn part1(input: &Vec<String>) -> usize {
+    let files = parse_input(input[0].as_str());
+    let free = parse_free_spans(input[0].as_str());
+    checksum(&compact(&files, &free, Strategy::Blocks))
+}
+
+fn part2(input: &Vec<String>) -> usize {
+    let files = parse_input(input[0].as_str());
+    let free = parse_free_spans(input[0].as_str());
+    checksum(&compact(&files, &free, Strategy::FirstFit))
 }
 
 #[cfg(test)]
 mod tests {
     use crate::util::file_io::get_test_input;
 
-    use super::{part1, part2};
+    use super::{
+        Fragmentation, Strategy, checksum, checksums, compact_blocks, compact_disk, compact_files,
+        compaction_report, fragmentation, parse_disk, parse_free_spans, parse_input, part1, part2,
+        render_disk,
+    };
 
     #[test]
     fn test_part1() {
@@ -123,4 +350,83 @@ mod tests {
         let res = part2(&input);
         assert_eq!(res, 2858);
     }
+
+    #[test]
+    fn test_compact_small() {
+        let files = parse_input("12345");
+        let free = parse_free_spans("12345");
+        assert_eq!(free, vec![(1, 2), (6, 4)]);
+        assert_eq!(checksum(&compact_blocks(&files, &free)), 60);
+        assert_eq!(
+            checksum(&compact_files(&files, &free, Strategy::FirstFit)),
+            132
+        );
+    }
+
+    #[test]
+    fn test_render_disk() {
+        let input = get_test_input(9);
+        let before = parse_disk(&input[0]);
+        assert_eq!(
+            render_disk(&before, 64),
+            "00...111...2...333.44.5555.6666.777.888899"
+        );
+        assert_eq!(render_disk(&before, 10), "00...111..... (32 more blocks)");
+        let after = compact_disk(&input[0], Strategy::Blocks);
+        assert_eq!(
+            render_disk(&after, 64),
+            "0099811188827773336446555566.............."
+        );
+        let after = compact_disk(&input[0], Strategy::FirstFit);
+        assert_eq!(
+            render_disk(&after, 64),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+    }
+
+    #[test]
+    fn test_fragmentation() {
+        let input = get_test_input(9);
+        let before = parse_disk(&input[0]);
+        let after = compact_disk(&input[0], Strategy::FirstFit);
+        assert_eq!(
+            fragmentation(&before, &after),
+            Fragmentation {
+                free_spans: 5,
+                largest_free_span: 5,
+                files_moved: 4,
+                files_in_place: 6,
+            }
+        );
+        let report = compaction_report(&input[0], Strategy::Blocks, 64);
+        assert!(
+            report.ends_with(
+                "free spans: 0, largest free span: 0, files moved: 4, files in place: 6"
+            )
+        );
+    }
+
+    #[test]
+    fn test_strategies() {
+        let input = get_test_input(9);
+        let res: Vec<(Strategy, usize)> = checksums(&input[0]);
+        assert_eq!(
+            res,
+            vec![
+                (Strategy::Blocks, 1928),
+                (Strategy::FirstFit, 2858),
+                (Strategy::BestFit, 2858),
+                (Strategy::WorstFit, 2858),
+                (Strategy::Defrag, 2453),
+            ]
+        );
+        let first_fit = compact_disk("1311121", Strategy::FirstFit);
+        assert_eq!(render_disk(&first_fit, 64), "0321......");
+        let best_fit = compact_disk("1311121", Strategy::BestFit);
+        assert_eq!(render_disk(&best_fit, 64), "021..3....");
+        let worst_fit = compact_disk("1113121", Strategy::WorstFit);
+        assert_eq!(render_disk(&worst_fit, 64), "01.32.....");
+        let defrag = compact_disk("1311121", Strategy::Defrag);
+        assert_eq!(render_disk(&defrag, 64), "0123......");
+    }
 }